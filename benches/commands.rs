@@ -0,0 +1,109 @@
+//! End-to-end timings for the most common commands against large databases.
+//!
+//! Each benchmark runs the compiled `todosh` binary inside a scratch
+//! directory holding a generated `data/db.csv`, so the numbers include
+//! process start-up, CSV parsing and table rendering.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+
+#[path = "../src/columns.rs"]
+mod columns;
+
+use columns::COLUMNS;
+
+const SIZES: [usize; 2] = [10_000, 100_000];
+
+fn scratch_dir(rows: usize) -> PathBuf {
+    let dir = env::temp_dir().join(format!("todosh-bench-{}-{rows}", std::process::id()));
+    fs::create_dir_all(dir.join("data")).unwrap();
+    dir
+}
+
+fn seed_database(dir: &Path, rows: usize) {
+    // The current header, so `create` appends rather than first rewriting
+    // a stale one. The columns after COMPLETED are left blank.
+    let mut csv = format!("{}\n", COLUMNS.join(","));
+    let blank = ",".repeat(COLUMNS.len() - 3);
+
+    for id in 1..=rows {
+        csv.push_str(&format!(
+            "{id},Benchmark task number {id},{}{blank}\n",
+            id % 3 == 0
+        ));
+    }
+
+    fs::write(dir.join("data/db.csv"), csv).unwrap();
+}
+
+fn todosh(dir: &Path, args: &[&str]) {
+    let status = Command::new(env!("CARGO_BIN_EXE_todosh"))
+        .args(args)
+        .current_dir(dir)
+        .stdout(Stdio::null())
+        .stdin(Stdio::null())
+        .status()
+        .unwrap();
+
+    assert!(status.success(), "todosh {args:?} failed");
+}
+
+fn bench_commands(c: &mut Criterion) {
+    let mut group = c.benchmark_group("commands");
+    group.sample_size(10);
+
+    for rows in SIZES {
+        let dir = scratch_dir(rows);
+
+        group.bench_with_input(BenchmarkId::new("list", rows), &rows, |b, &rows| {
+            seed_database(&dir, rows);
+            b.iter(|| todosh(&dir, &["list"]));
+        });
+
+        group.bench_with_input(
+            BenchmarkId::new("list --stream", rows),
+            &rows,
+            |b, &rows| {
+                seed_database(&dir, rows);
+                b.iter(|| todosh(&dir, &["list", "--stream"]));
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("list --only-incomplete", rows),
+            &rows,
+            |b, &rows| {
+                seed_database(&dir, rows);
+                b.iter(|| todosh(&dir, &["list", "--only-incomplete"]));
+            },
+        );
+
+        group.bench_with_input(BenchmarkId::new("create", rows), &rows, |b, &rows| {
+            b.iter_batched(
+                || seed_database(&dir, rows),
+                |_| todosh(&dir, &["create", "Benchmark task"]),
+                BatchSize::PerIteration,
+            );
+        });
+
+        group.bench_with_input(BenchmarkId::new("complete", rows), &rows, |b, &rows| {
+            b.iter_batched(
+                || seed_database(&dir, rows),
+                |_| todosh(&dir, &["complete", "1"]),
+                BatchSize::PerIteration,
+            );
+        });
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_commands);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+use std::{fs::OpenOptions, io::Write};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::config::Config;
+
+/// One line of the audit log. The log is only ever appended to; todosh
+/// never reads it back.
+#[derive(Serialize)]
+struct Entry<'a> {
+    at: DateTime<Utc>,
+    command: &'a str,
+    ids: &'a [String],
+}
+
+/// Appends a mutation to the audit log when auditing is enabled. A failed
+/// write is reported but never aborts the change it describes.
+pub fn record(config: &Config, command: &str, ids: &[String]) {
+    if !config.audit {
+        return;
+    }
+
+    let entry = Entry {
+        at: Utc::now(),
+        command,
+        ids,
+    };
+
+    let written = serde_json::to_string(&entry)
+        .map_err(std::io::Error::other)
+        .and_then(|line| {
+            OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(config.audit_path())
+                .and_then(|mut file| writeln!(file, "{line}"))
+        });
+
+    if let Err(e) = written {
+        eprintln!("warning: Failed to write audit log: {e}");
+    }
+}
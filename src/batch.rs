@@ -0,0 +1,278 @@
+//! `todosh run <file>`: one command per line, applied to the database in
+//! memory and saved once at the end.
+
+use std::{collections::HashSet, fs, iter, process::ExitCode};
+
+use clap::ArgMatches;
+
+use crate::{
+    Todo, append_to_archive, apply_args, audit, check_active_limit, cli, config::Config,
+    error::AppError, i18n, input_text, insert_subtask, insert_todos, mark_completed,
+    records_to_rewrite, reindex, remember, remove_todo, render_todos, repl::split_words,
+    resolve_in, set_task, settle_completion, sort_listed, todo_from_args, write_to_database,
+};
+
+/// Commands a script may contain.
+static COMMANDS: [&str; 6] = ["create", "add-sub", "complete", "update", "delete", "list"];
+
+/// Flags that pick another database. A script is loaded from and saved to
+/// one, so a line can't switch away from it.
+static DATABASE_FLAGS: [&str; 4] = ["db", "list", "archived", "backend"];
+
+/// What a script line did.
+enum Outcome {
+    Changed(String),
+    Unchanged(String),
+    Listed,
+}
+
+/// What `complete` lines leave to do once the script is saved.
+#[derive(Default)]
+struct Completions {
+    /// Todos `auto_archive_on_complete` took out of the database.
+    archived: Vec<Todo>,
+    /// The IDs the last `complete` line marked done, for `complete --undo`.
+    last: Option<Vec<String>>,
+}
+
+pub fn run(config: &Config, path: &str) -> ExitCode {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => AppError::ReadFailed {
+            path: path.to_owned(),
+            reason: e.to_string(),
+        }
+        .exit(config),
+    };
+
+    let mut records = records_to_rewrite(config).unwrap_or_else(|e| e.exit(config));
+    let mut completions = Completions::default();
+    let mut changed = false;
+    let (mut succeeded, mut failed) = (0, 0);
+
+    for (number, line) in (1..).zip(contents.lines()) {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let args = iter::once("todosh".to_owned()).chain(split_words(line));
+        let result = match cli().get_matches_from_safe(args) {
+            Ok(args) if let Some(flag) = DATABASE_FLAGS.iter().find(|f| args.is_present(f)) => {
+                Err(AppError::FlagInScript {
+                    flag: (*flag).to_owned(),
+                }
+                .to_string())
+            }
+            Ok(args) => {
+                let mut line_config = config.clone();
+                apply_args(&mut line_config, &args);
+                run_line(&line_config, &args, &mut records, &mut completions)
+            }
+            Err(e) => Err(e.message.lines().next().unwrap_or_default().to_owned()),
+        };
+
+        match result {
+            Ok(Outcome::Changed(outcome)) => {
+                changed = true;
+                succeeded += 1;
+                report_ok(config, number, &outcome);
+            }
+            Ok(Outcome::Unchanged(outcome)) => {
+                succeeded += 1;
+                report_ok(config, number, &outcome);
+            }
+            Ok(Outcome::Listed) => succeeded += 1,
+            Err(message) => {
+                failed += 1;
+                println!(
+                    "{}",
+                    i18n::text(
+                        config,
+                        "script-failed",
+                        &[("line", &number), ("message", &message)],
+                    )
+                );
+            }
+        }
+    }
+
+    if changed {
+        if !completions.archived.is_empty() {
+            append_to_archive(config, &completions.archived);
+        }
+
+        write_to_database(config, records);
+
+        if let Some(ids) = completions.last {
+            remember::record_completion(config, &ids);
+        }
+    }
+
+    println!(
+        "{}",
+        i18n::text(
+            config,
+            "script-summary",
+            &[("succeeded", &succeeded), ("failed", &failed)],
+        )
+    );
+
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// The report for a line that went through.
+fn report_ok(config: &Config, line: usize, outcome: &str) {
+    println!(
+        "{}",
+        i18n::text(
+            config,
+            "script-ok",
+            &[("line", &line), ("outcome", &outcome)]
+        )
+    );
+}
+
+/// Applies one script line to `records` and says what it did.
+fn run_line(
+    config: &Config,
+    args: &ArgMatches,
+    records: &mut Vec<Todo>,
+    completions: &mut Completions,
+) -> Result<Outcome, String> {
+    let command = args.value_of("command").unwrap_or(&config.default_command);
+
+    if !COMMANDS.contains(&command) {
+        return Err(AppError::CommandInScript {
+            command: command.to_owned(),
+            known: COMMANDS.map(str::to_owned).to_vec(),
+        }
+        .to_string());
+    }
+
+    let id = || -> Result<String, String> {
+        let value = args
+            .value_of("input")
+            .ok_or_else(|| AppError::MissingId.to_string())?;
+        resolve_in(records, value)
+            .map(|id| id.to_string())
+            .map_err(|e| e.to_string())
+    };
+
+    let outcome = match command {
+        "list" => {
+            let mut listed: Vec<Todo> = records
+                .iter()
+                .filter(|todo| config.shows(todo.completed))
+                .filter(|todo| !config.ignore.matches(&todo.task, todo.tag_list()))
+                .cloned()
+                .collect();
+
+            sort_listed(config, &mut listed);
+            println!("{}", render_todos(config, listed));
+            return Ok(Outcome::Listed);
+        }
+        "create" => {
+            let text = input_text(args, 0).ok_or_else(|| AppError::MissingText.to_string())?;
+            let active = records.iter().filter(|todo| !todo.completed).count();
+            check_active_limit(config, active, 1).map_err(|e| e.to_string())?;
+            let ids = insert_todos(
+                records,
+                vec![todo_from_args(config, args, &text, "batch")],
+                false,
+            );
+            audit::record(config, "create", &ids);
+            i18n::text(config, "script-created", &[("ids", &ids.join(", "))])
+        }
+        "add-sub" => {
+            let parent_id = id()?;
+            let text = input_text(args, 1).ok_or_else(|| AppError::MissingText.to_string())?;
+            let todo = todo_from_args(config, args, &text, "batch");
+            let active = records.iter().filter(|todo| !todo.completed).count();
+            check_active_limit(config, active, 1).map_err(|e| e.to_string())?;
+            let new_id = insert_subtask(records, &parent_id, todo).map_err(|e| e.to_string())?;
+            audit::record(config, "add-sub", std::slice::from_ref(&new_id));
+            i18n::text(
+                config,
+                "script-created-under",
+                &[("id", &new_id), ("parent", &parent_id)],
+            )
+        }
+        "complete" => {
+            let id = id()?;
+            let were_completed: HashSet<String> = records
+                .iter()
+                .filter(|todo| todo.completed)
+                .map(|todo| todo.id.clone())
+                .collect();
+            let completed =
+                mark_completed(records, &id, args.value_of("note")).ok_or_else(|| {
+                    let error = if were_completed.contains(&id) {
+                        AppError::AlreadyCompleted { id: id.clone() }
+                    } else {
+                        AppError::NotFound { id: id.clone() }
+                    };
+                    error.to_string()
+                })?;
+            audit::record(config, "complete", &completed);
+
+            let archived = settle_completion(config, records, &were_completed);
+
+            // As with `complete`, archiving renumbers what's left, so there's
+            // nothing to undo.
+            completions.last = Some(if archived.is_empty() {
+                completed.clone()
+            } else {
+                Vec::new()
+            });
+
+            if !archived.is_empty() {
+                let ids: Vec<String> = archived.iter().map(|todo| todo.id.clone()).collect();
+                audit::record(config, "archive", &ids);
+                *records = reindex(std::mem::take(records));
+                completions.archived.extend(archived);
+            }
+
+            i18n::text(
+                config,
+                "script-completed",
+                &[("ids", &completed.join(", "))],
+            )
+        }
+        "update" => {
+            let id = id()?;
+            let text = input_text(args, 1)
+                .filter(|text| !text.trim().is_empty())
+                .ok_or_else(|| AppError::MissingText.to_string())?;
+
+            if !set_task(records, &id, &text) {
+                return Ok(Outcome::Unchanged(i18n::text(
+                    config,
+                    "script-unchanged",
+                    &[("id", &id), ("text", &text)],
+                )));
+            }
+
+            audit::record(config, "update", std::slice::from_ref(&id));
+            i18n::text(config, "script-updated", &[("id", &id)])
+        }
+        "delete" => {
+            let id = id()?;
+            let deleted = remove_todo(records, &id).map_err(|e| e.to_string())?;
+            audit::record(config, "delete", std::slice::from_ref(&id));
+            i18n::text(
+                config,
+                "script-deleted",
+                &[("id", &id), ("task", &deleted.task)],
+            )
+        }
+        _ => unreachable!("checked against COMMANDS"),
+    };
+
+    Ok(Outcome::Changed(outcome))
+}
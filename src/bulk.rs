@@ -0,0 +1,136 @@
+//! `complete --stdin` and `delete --stdin`: apply one command to every ID
+//! piped in, in a single write, e.g.
+//! `todosh list --porcelain | cut -f1 | todosh complete --stdin`.
+
+use std::{collections::HashSet, process::ExitCode};
+
+use crate::{
+    Todo, append_to_archive, audit, config::Config, derive_parent_completion, error::AppError,
+    get_reader, i18n, mark_completed, read_stdin_capped, reindex, remember, resolve_in,
+    write_to_database,
+};
+
+/// Whitespace- or newline-separated IDs from stdin.
+pub fn read_ids(config: &Config) -> Vec<String> {
+    let contents = read_stdin_capped(config).unwrap_or_else(|e| {
+        AppError::ReadInput {
+            reason: e.to_string(),
+        }
+        .exit(config)
+    });
+
+    let ids: Vec<String> = contents.split_whitespace().map(str::to_owned).collect();
+
+    if ids.is_empty() {
+        AppError::MissingId.exit(config)
+    }
+
+    ids
+}
+
+/// Completes every todo in `ids` that is still pending, with its
+/// subtasks. Fails if any ID was skipped.
+pub fn complete(config: &Config, ids: &[String], note: Option<&str>) -> ExitCode {
+    let mut records: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+    let were_completed: HashSet<String> = records
+        .iter()
+        .filter(|record| record.completed)
+        .map(|record| record.id.clone())
+        .collect();
+
+    let mut completed = Vec::new();
+    let mut skipped = Vec::new();
+
+    for value in ids {
+        let marked = resolve_in(&records, value)
+            .ok()
+            .and_then(|id| mark_completed(&mut records, &id.to_string(), note));
+
+        match marked {
+            Some(ids) => completed.extend(ids),
+            None => skipped.push(value.as_str()),
+        }
+    }
+
+    if !completed.is_empty() {
+        derive_parent_completion(&mut records);
+        let mut archived = Vec::new();
+
+        if config.auto_archive_on_complete {
+            (archived, records) = records
+                .into_iter()
+                .partition(|record| record.completed && !were_completed.contains(&record.id));
+        }
+
+        if !archived.is_empty() {
+            append_to_archive(config, &archived);
+            records = reindex(records);
+        }
+
+        write_to_database(config, records);
+        audit::record(config, "complete", &completed);
+
+        if archived.is_empty() {
+            remember::record_completion(config, &completed);
+        }
+    }
+
+    report(config, "bulk-completed", &completed, &skipped)
+}
+
+/// Deletes every todo in `ids`, resolving them all before anything is
+/// renumbered. Fails if any ID was skipped.
+pub fn delete(config: &Config, ids: &[String]) -> ExitCode {
+    let records: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+    let mut doomed: Vec<String> = Vec::new();
+    let mut skipped = Vec::new();
+
+    for value in ids {
+        match resolve_in(&records, value).map(|id| id.to_string()) {
+            Ok(id) if records.iter().any(|r| r.id == id) && !doomed.contains(&id) => {
+                doomed.push(id)
+            }
+            _ => skipped.push(value.as_str()),
+        }
+    }
+
+    if !doomed.is_empty() {
+        let remaining = records
+            .into_iter()
+            .filter(|record| !doomed.contains(&record.id))
+            .collect();
+
+        write_to_database(config, reindex(remaining));
+        audit::record(config, "delete", &doomed);
+    }
+
+    report(config, "bulk-deleted", &doomed, &skipped)
+}
+
+/// Prints what was applied and skipped with the catalog messages `key`,
+/// `<key>-none` and `<key>-skipped`.
+fn report(config: &Config, key: &str, applied: &[String], skipped: &[&str]) -> ExitCode {
+    let message = match applied {
+        [] => i18n::text(config, &format!("{key}-none"), &[]),
+        _ => i18n::text(
+            config,
+            key,
+            &[("count", &applied.len()), ("ids", &applied.join(", "))],
+        ),
+    };
+    println!("{message}");
+
+    if skipped.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+
+    println!(
+        "{}",
+        i18n::text(
+            config,
+            &format!("{key}-skipped"),
+            &[("count", &skipped.len()), ("ids", &skipped.join(", "))],
+        )
+    );
+    ExitCode::FAILURE
+}
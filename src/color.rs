@@ -0,0 +1,41 @@
+use std::{
+    env,
+    io::{self, IsTerminal},
+};
+
+/// Colors a todo can be given with `--task-color`.
+pub static PALETTE: [&str; 6] = ["red", "green", "yellow", "blue", "magenta", "cyan"];
+
+/// ANSI foreground escape for a palette color, or `None` for an empty or
+/// unknown name so old rows render uncolored.
+pub fn ansi_code(name: &str) -> Option<&'static str> {
+    match name {
+        "red" => Some("\u{1b}[31m"),
+        "green" => Some("\u{1b}[32m"),
+        "yellow" => Some("\u{1b}[33m"),
+        "blue" => Some("\u{1b}[34m"),
+        "magenta" => Some("\u{1b}[35m"),
+        "cyan" => Some("\u{1b}[36m"),
+        _ => None,
+    }
+}
+
+pub static RESET: &str = "\u{1b}[39m";
+
+/// Values of `--color`, which controls whether output is colored.
+pub static COLOR_MODES: [&str; 3] = ["always", "auto", "never"];
+
+/// Whether to write colors, given `--color`: `always` and `never` decide
+/// outright, and `auto` or no flag leaves it to `stdout_supports_color`.
+pub fn color_enabled(flag: Option<&str>) -> bool {
+    match flag {
+        Some("always") => true,
+        Some("never") => false,
+        _ => stdout_supports_color(),
+    }
+}
+
+/// Colors are only written to a terminal, and never when `NO_COLOR` is set.
+fn stdout_supports_color() -> bool {
+    env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()) && io::stdout().is_terminal()
+}
@@ -0,0 +1,20 @@
+//! The database columns, kept apart so the benchmarks can write the same
+//! header.
+
+/// Column names written to the database header, in field order.
+pub static COLUMNS: [&str; 14] = [
+    "ID",
+    "TASK",
+    "COMPLETED",
+    "COMPLETED_AT",
+    "NOTES",
+    "TAGS",
+    "COLOR",
+    "CREATED_AT",
+    "PRIORITY",
+    "PARENT_ID",
+    "DUE",
+    "ESTIMATE",
+    "SOURCE",
+    "TIMES_COMPLETED",
+];
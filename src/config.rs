@@ -0,0 +1,610 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    process::exit,
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, de::Error};
+use unicode_width::UnicodeWidthStr;
+
+use crate::{i18n::Lang, ignore::IgnoreList, priority::Priority, timezone::Timezone};
+
+static CONFIG_PATH: &str = "data/config.toml";
+static DATABASE_PATH: &str = "data/db.csv";
+
+/// Preferences `todosh set` can change: the name given on the command line,
+/// the key written to the config file, and older keys it replaces.
+pub static SETTINGS: [(&str, &str, &[&str]); 3] = [
+    ("hide-completed", "hide_completed", &["only_incomplete"]),
+    ("default-priority", "default_priority", &[]),
+    ("daily-goal", "daily_goal", &[]),
+];
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+    Html,
+}
+
+/// Field `list --group-by` splits the table on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Tag,
+    Completed,
+    Priority,
+}
+
+/// Fields `search --field` looks in; all of them by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchField {
+    Task,
+    Tags,
+    Notes,
+    #[default]
+    All,
+}
+
+/// Where `list` puts completed todos relative to pending ones, keeping
+/// the order within each group.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CompletedOrder {
+    /// Interleaved, in ID (or `--sort`) order.
+    #[default]
+    Mixed,
+    First,
+    Last,
+}
+
+/// File format of the database, see `storage`.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl Backend {
+    pub fn parse(value: &str) -> Option<Backend> {
+        match value.to_ascii_lowercase().as_str() {
+            "csv" => Some(Backend::Csv),
+            "json" => Some(Backend::Json),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Csv => "csv",
+            Backend::Json => "json",
+        }
+    }
+}
+
+/// Keys `list --sort` accepts, in the order they're documented.
+pub static SORT_KEYS: [&str; 7] = [
+    "completed",
+    "completed_at",
+    "created",
+    "due",
+    "priority",
+    "id",
+    "task",
+];
+
+/// Field `list --sort` orders by. Pending todos come before completed
+/// ones, dates run oldest first (but completion times newest first) and
+/// priorities highest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortBy {
+    Completed,
+    CompletedAt,
+    Created,
+    Due,
+    Priority,
+    Id,
+    Task,
+}
+
+impl SortBy {
+    /// Parses a comma-separated spec such as `completed,created`.
+    pub fn parse_spec(spec: &str) -> Result<Vec<SortBy>, String> {
+        spec.split(',')
+            .map(|key| match key.trim().to_ascii_lowercase().as_str() {
+                "completed" => Ok(SortBy::Completed),
+                "completed_at" => Ok(SortBy::CompletedAt),
+                "created" => Ok(SortBy::Created),
+                "due" => Ok(SortBy::Due),
+                "priority" => Ok(SortBy::Priority),
+                "id" => Ok(SortBy::Id),
+                "task" => Ok(SortBy::Task),
+                _ => Err(format!(
+                    "unknown sort key {key:?}, expected a comma-separated list of: {}",
+                    SORT_KEYS.join(", ")
+                )),
+            })
+            .collect()
+    }
+}
+
+/// Column labels shown above `list` tables. The CSV header the database
+/// is written with never changes.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Headers {
+    pub id: String,
+    pub task: String,
+    pub completed: String,
+    pub age: String,
+    pub due: String,
+    pub source: String,
+}
+
+impl Default for Headers {
+    fn default() -> Headers {
+        Headers {
+            id: "id".to_owned(),
+            task: "task".to_owned(),
+            completed: "completed".to_owned(),
+            age: "age".to_owned(),
+            due: "due".to_owned(),
+            source: "source".to_owned(),
+        }
+    }
+}
+
+impl Headers {
+    /// Labels for the columns every `list` table has, in order.
+    pub fn labels(&self) -> [&str; 3] {
+        [&self.id, &self.task, &self.completed]
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(default)]
+pub struct Config {
+    /// CSV file holding the todos; overridden by `TODOSH_DB` and `--db`.
+    pub database: PathBuf,
+    /// Command to run when `todosh` is invoked without one.
+    pub default_command: String,
+    /// Create an empty database when the file doesn't exist yet. Turned
+    /// off by `--no-auto-create`, so a mistyped path fails loudly.
+    pub auto_create: bool,
+    /// How the database is stored; also TODOSH_BACKEND or `--backend`.
+    pub backend: Backend,
+    /// Save the database and archive encrypted with a passphrase, from
+    /// TODOSH_PASSPHRASE or asked for on the terminal.
+    pub encrypt: bool,
+    /// Field separator used for both reading and writing the database.
+    #[serde(deserialize_with = "deserialize_delimiter")]
+    pub delimiter: u8,
+    /// Language of command messages; `--lang`, else this, else the locale.
+    pub lang: Option<Lang>,
+    /// Read a database holding invalid UTF-8 by replacing the bad bytes,
+    /// with a warning, instead of refusing to load it.
+    pub lossy_utf8: bool,
+    /// Hide completed todos from `list` unless `--all` is passed. Written
+    /// as `hide_completed` by `todosh set hide-completed`.
+    #[serde(alias = "hide_completed")]
+    pub only_incomplete: bool,
+    /// Longest line a prompt or the REPL reads, and most `create
+    /// --input-file -` reads from stdin, in bytes.
+    pub max_input_bytes: usize,
+    /// Priority for new todos created without `--priority`.
+    pub default_priority: Priority,
+    /// Where `list` puts completed todos; `list --completed-first`
+    /// overrides it.
+    pub completed_order: CompletedOrder,
+    /// Print a `done/total completed (n%)` line under `list`.
+    pub show_footer: bool,
+    /// Lines of `list` output on a terminal past which it goes through
+    /// `$PAGER`; 0 never pages.
+    pub max_rows: usize,
+    /// `--pager` (`Some(true)`) or `--no-pager`, overriding `max_rows`.
+    #[serde(skip)]
+    pub pager: Option<bool>,
+    /// Most incomplete todos `create` allows, a WIP limit; 0 for none.
+    /// `--force` creates past it.
+    pub max_active: usize,
+    /// Todos to complete each day, shown as `3/5 today` under `list` and
+    /// `stats`; 0 for no goal. Set with `todosh goal set 5`.
+    pub daily_goal: u32,
+    /// How `list`/`show` output and errors are printed.
+    pub format: OutputFormat,
+    /// Move todos to the archive file as soon as `complete` marks them done.
+    pub auto_archive_on_complete: bool,
+    /// Make `list` reuse the filters and sort it was last given when run
+    /// with none; `list --default` forgets them.
+    pub remember_list_filters: bool,
+    /// Append every change to `audit.jsonl` next to the database; also
+    /// enabled with `--audit`.
+    pub audit: bool,
+    /// Shown in the `completed` column of `list` for done todos; also
+    /// `--completed-symbol`.
+    #[serde(deserialize_with = "deserialize_symbol")]
+    pub completed_symbol: String,
+    /// Shown in the `completed` column for pending todos; also
+    /// `--pending-symbol`.
+    #[serde(deserialize_with = "deserialize_symbol")]
+    pub pending_symbol: String,
+    /// Zone `show` displays timestamps in: `local` (the default), `utc` or a
+    /// fixed offset such as `+02:00`.
+    pub timezone: Timezone,
+    /// `--pretty`: indent JSON output instead of keeping it on one line.
+    #[serde(skip)]
+    pub pretty: bool,
+    /// `--utc`: show timestamps exactly as stored, in UTC RFC 3339.
+    #[serde(skip)]
+    pub utc: bool,
+    /// Display labels for the `list` columns, from a `[headers]` table.
+    pub headers: Headers,
+    /// Task texts for `create --template NAME`, from a `[templates]` table.
+    pub templates: BTreeMap<String, String>,
+    /// Whether output may contain ANSI colors; detected at startup.
+    #[serde(skip)]
+    pub color: bool,
+    /// `list --completed`: show only completed todos.
+    #[serde(skip)]
+    pub only_completed: bool,
+    /// `list --newest`: order by creation time, most recent first.
+    #[serde(skip)]
+    pub newest: bool,
+    /// `--quiet`: keep output to the essentials.
+    #[serde(skip)]
+    pub quiet: bool,
+    /// `list --group-by`: render one table per group.
+    #[serde(skip)]
+    pub group_by: Option<GroupBy>,
+    /// `list --sort`: order by these fields instead of by ID, each one
+    /// breaking ties left by the one before.
+    #[serde(skip)]
+    pub sort: Vec<SortBy>,
+    /// `list --nulls first`: put todos missing a sorted date first.
+    #[serde(skip)]
+    pub nulls_first: bool,
+    /// `list --show-age`: add a column with how long each todo has been open.
+    #[serde(skip)]
+    pub show_age: bool,
+    /// `list --tree`: connect subtasks to their parents with `├─`/`└─`.
+    #[serde(skip)]
+    pub tree: bool,
+    /// `list --show-source`: add a column with how each todo was created.
+    #[serde(skip)]
+    pub show_source: bool,
+    /// `list --max-width`: wrap the table to at most this many columns.
+    #[serde(skip)]
+    pub max_width: Option<usize>,
+    /// `--preview`: show the result of a change instead of saving it.
+    #[serde(skip)]
+    pub preview: bool,
+    /// `--strict`: refuse a database with any row that doesn't parse,
+    /// instead of skipping it.
+    #[serde(skip)]
+    pub strict: bool,
+    /// `list --debug-layout`: report column widths before the table.
+    #[serde(skip)]
+    pub debug_layout: bool,
+    /// `--force`: create todos past `max_active`.
+    #[serde(skip)]
+    pub force: bool,
+    /// `--read-only`: fail instead of writing anything.
+    #[serde(skip)]
+    pub read_only: bool,
+    /// `--no-ignore`: leave `ignore` empty.
+    #[serde(skip)]
+    pub no_ignore: bool,
+    /// Patterns hiding todos from `list`, loaded unless `--no-ignore`.
+    #[serde(skip)]
+    pub ignore: IgnoreList,
+    /// `search`: only list todos whose `search_field` contains this,
+    /// already lowercased.
+    #[serde(skip)]
+    pub query: Option<String>,
+    /// `search --field`: where to look for `query`.
+    #[serde(skip)]
+    pub search_field: SearchField,
+    /// `list --new`: only todos created or completed after this.
+    #[serde(skip)]
+    pub since: Option<DateTime<Utc>>,
+    /// `list --older-than`/`--newer-than`: only todos created before or
+    /// after these.
+    #[serde(skip)]
+    pub created_before: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    pub created_after: Option<DateTime<Utc>>,
+    /// REPL handles shown in an extra `list` column, by todo ID.
+    #[serde(skip)]
+    pub handles: HashMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            database: PathBuf::from(DATABASE_PATH),
+            default_command: "list".to_owned(),
+            auto_create: true,
+            backend: Backend::default(),
+            encrypt: false,
+            delimiter: b',',
+            lang: None,
+            lossy_utf8: true,
+            only_incomplete: false,
+            default_priority: Priority::default(),
+            max_input_bytes: 64 * 1024,
+            completed_order: CompletedOrder::default(),
+            show_footer: true,
+            max_rows: 50,
+            pager: None,
+            max_active: 0,
+            daily_goal: 0,
+            format: OutputFormat::Table,
+            auto_archive_on_complete: false,
+            remember_list_filters: false,
+            audit: false,
+            completed_symbol: "true".to_owned(),
+            pending_symbol: "false".to_owned(),
+            timezone: Timezone::default(),
+            utc: false,
+            pretty: false,
+            headers: Headers::default(),
+            templates: BTreeMap::new(),
+            color: false,
+            only_completed: false,
+            newest: false,
+            quiet: false,
+            group_by: None,
+            sort: Vec::new(),
+            nulls_first: false,
+            show_age: false,
+            show_source: false,
+            tree: false,
+            max_width: None,
+            preview: false,
+            strict: false,
+            force: false,
+            read_only: false,
+            no_ignore: false,
+            debug_layout: false,
+            ignore: IgnoreList::default(),
+            query: None,
+            search_field: SearchField::default(),
+            since: None,
+            created_before: None,
+            created_after: None,
+            handles: HashMap::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Config {
+        let mut config = Config::from_file();
+        config.apply_env(|name| env::var_os(name));
+
+        config
+    }
+
+    /// Layers the TODOSH_* variables `var` looks up over the config file.
+    /// Besides the documented ones, the REPL passes its own settings to the
+    /// commands it runs this way.
+    pub fn apply_env(&mut self, var: impl Fn(&str) -> Option<OsString>) {
+        let text = |name| var(name).and_then(|value| value.into_string().ok());
+        let flag = |name| text(name).is_some_and(|value| !value.is_empty() && value != "0");
+
+        if let Some(db) = var("TODOSH_DB") {
+            self.database = PathBuf::from(db);
+        }
+
+        if let Some(backend) = text("TODOSH_BACKEND") {
+            self.backend = Backend::parse(&backend).unwrap_or_else(|| {
+                eprintln!("TODOSH_BACKEND must be csv or json, got {backend:?}");
+                exit(1);
+            });
+        }
+
+        self.use_backend_extension();
+
+        if let Some(lang) = text("TODOSH_LANG").as_deref().and_then(Lang::parse) {
+            self.lang = Some(lang);
+        }
+
+        if self.lang.is_none() {
+            self.lang = Lang::from_env();
+        }
+
+        if let Some(delimiter) = text("TODOSH_DELIMITER") {
+            self.delimiter = parse_delimiter(&delimiter).unwrap_or_else(|| {
+                eprintln!("TODOSH_DELIMITER must be a single ASCII character, got {delimiter:?}");
+                exit(1);
+            });
+        }
+
+        self.read_only |= flag("TODOSH_READ_ONLY");
+        self.strict |= flag("TODOSH_STRICT");
+        self.audit |= flag("TODOSH_AUDIT");
+        self.no_ignore |= flag("TODOSH_NO_IGNORE");
+    }
+
+    /// Whether `list` shows a todo with this completion state.
+    pub fn shows(&self, completed: bool) -> bool {
+        if completed {
+            !self.only_incomplete
+        } else {
+            !self.only_completed
+        }
+    }
+
+    /// Text for the `completed` column of `list`.
+    pub fn completion_symbol(&self, completed: bool) -> &str {
+        if completed {
+            &self.completed_symbol
+        } else {
+            &self.pending_symbol
+        }
+    }
+
+    /// With the JSON backend the default database is `data/db.json`, so
+    /// switching backends doesn't read one format as the other.
+    pub fn use_backend_extension(&mut self) {
+        if self.backend == Backend::Json && self.database == Path::new(DATABASE_PATH) {
+            self.database.set_extension("json");
+        }
+    }
+
+    /// Completed todos moved out by `archive` belong to one database:
+    /// `data/db.csv` archives to `data/db.archive.csv`.
+    pub fn archive_path(&self) -> PathBuf {
+        self.database.with_extension("archive.csv")
+    }
+
+    /// Saves a preference to the config file, keeping the other settings.
+    /// Returns `false` if `name` isn't one of `SETTINGS`.
+    pub fn save_setting(name: &str, value: toml::Value) -> bool {
+        let Some((_, key, replaces)) = SETTINGS.iter().find(|(n, ..)| *n == name) else {
+            return false;
+        };
+
+        let mut table = match fs::read_to_string(CONFIG_PATH) {
+            Ok(contents) => contents.parse::<toml::Table>().unwrap_or_else(|e| {
+                eprintln!("Failed to parse {CONFIG_PATH}: {e}");
+                exit(1);
+            }),
+            Err(_) => toml::Table::new(),
+        };
+
+        for old in *replaces {
+            table.remove(*old);
+        }
+        table.insert((*key).to_owned(), value);
+
+        if let Some(dir) = Path::new(CONFIG_PATH).parent()
+            && let Err(e) = fs::create_dir_all(dir)
+        {
+            eprintln!("Failed to create config directory: {e}");
+            exit(1);
+        }
+
+        if let Err(e) = fs::write(CONFIG_PATH, table.to_string()) {
+            eprintln!("Failed to write {CONFIG_PATH}: {e}");
+            exit(1);
+        }
+
+        true
+    }
+
+    /// Named lists are databases side by side: list `home` is `home.csv`,
+    /// or `home.json` when the current database is JSON, next to it.
+    /// `None` unless the name is made of letters, digits, `-` and `_`.
+    pub fn list_path(&self, name: &str) -> Option<PathBuf> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+        let extension = self.database.extension().unwrap_or("csv".as_ref());
+
+        valid.then(|| self.database.with_file_name(name).with_extension(extension))
+    }
+
+    /// Audit entries are appended to a JSON Lines file next to the database.
+    pub fn audit_path(&self) -> PathBuf {
+        self.database.with_file_name("audit.jsonl")
+    }
+
+    /// The list's name and description live beside it: `data/db.csv`
+    /// reads `data/db.meta.toml`.
+    pub fn meta_path(&self) -> PathBuf {
+        self.database.with_extension("meta.toml")
+    }
+
+    /// Filters remembered by `remember_list_filters` belong to one
+    /// database: `data/db.csv` keeps them in `data/db.liststate`.
+    pub fn list_state_path(&self) -> PathBuf {
+        self.database.with_extension("liststate")
+    }
+
+    /// The ignore file belongs to one database: `data/db.csv` reads
+    /// `data/db.todoignore`.
+    pub fn ignore_path(&self) -> PathBuf {
+        self.database.with_extension("todoignore")
+    }
+
+    /// Where settings are read from, relative to the working directory.
+    pub fn file_path() -> &'static Path {
+        Path::new(CONFIG_PATH)
+    }
+
+    fn from_file() -> Config {
+        if !Path::new(CONFIG_PATH).is_file() {
+            return Config::default();
+        }
+
+        let contents = match fs::read_to_string(CONFIG_PATH) {
+            Ok(contents) => contents,
+            Err(e) => {
+                eprintln!("Failed to read config: {e}");
+                exit(1);
+            }
+        };
+
+        match toml::from_str(&contents) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Failed to parse {CONFIG_PATH}: {e}");
+                exit(1);
+            }
+        }
+    }
+}
+
+/// Accepts a single ASCII character, or `tab`/`\t` for TSV files.
+fn parse_delimiter(value: &str) -> Option<u8> {
+    match value {
+        "tab" | "\\t" => Some(b'\t'),
+        _ if value.len() == 1 && value.is_ascii() => Some(value.as_bytes()[0]),
+        _ => None,
+    }
+}
+
+/// Longest completion symbol allowed, in terminal columns.
+const MAX_SYMBOL_WIDTH: usize = 12;
+
+/// Checks a completion symbol renders as one table cell of known width:
+/// non-empty, on one line, free of control characters and short.
+pub fn validate_symbol(symbol: &str) -> Result<(), String> {
+    if symbol.is_empty() {
+        Err("the symbol can't be empty".to_owned())
+    } else if symbol.chars().any(char::is_control) {
+        Err(format!(
+            "{symbol:?} contains a line break or control character"
+        ))
+    } else if symbol.width() > MAX_SYMBOL_WIDTH {
+        Err(format!(
+            "{symbol:?} is wider than {MAX_SYMBOL_WIDTH} columns"
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn deserialize_symbol<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let value = String::deserialize(deserializer)?;
+
+    validate_symbol(&value).map_err(D::Error::custom)?;
+    Ok(value)
+}
+
+fn deserialize_delimiter<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+    let value = String::deserialize(deserializer)?;
+
+    parse_delimiter(&value)
+        .ok_or_else(|| D::Error::custom("delimiter must be a single ASCII character"))
+}
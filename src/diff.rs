@@ -0,0 +1,151 @@
+//! `todosh diff <other>`: what changed in the current database relative to
+//! a backup or another list.
+//!
+//! IDs are renumbered on every delete, so todos are matched by their
+//! creation time. Rows written before timestamps existed fall back to the
+//! task text, then to the ID.
+
+use crate::{
+    Todo,
+    color::{RESET, ansi_code},
+    config::Config,
+    get_reader, i18n, list_or_file,
+};
+
+#[derive(Debug)]
+pub enum Change<'a> {
+    Added(&'a Todo),
+    Removed(&'a Todo),
+    Completed(&'a Todo),
+    Reopened(&'a Todo),
+    Retitled { old: &'a Todo, new: &'a Todo },
+}
+
+/// Changes from `old` to `new`: todos in `new` order, then the removed ones
+/// in `old` order.
+pub fn changes<'a>(old: &'a [Todo], new: &'a [Todo]) -> Vec<Change<'a>> {
+    let mut matched: Vec<Option<usize>> = vec![None; new.len()];
+    let mut taken = vec![false; old.len()];
+
+    let passes: [fn(&Todo, &Todo) -> bool; 3] = [
+        |a, b| a.created_at.is_some() && a.created_at == b.created_at,
+        |a, b| (a.created_at.is_none() || b.created_at.is_none()) && a.task == b.task,
+        |a, b| a.created_at.is_none() && b.created_at.is_none() && a.id == b.id,
+    ];
+
+    for same in passes {
+        for (n, todo) in new.iter().enumerate() {
+            if matched[n].is_some() {
+                continue;
+            }
+
+            if let Some(o) = (0..old.len()).find(|&o| !taken[o] && same(&old[o], todo)) {
+                taken[o] = true;
+                matched[n] = Some(o);
+            }
+        }
+    }
+
+    let mut changes = Vec::new();
+
+    for (todo, matched) in new.iter().zip(matched) {
+        let Some(o) = matched else {
+            changes.push(Change::Added(todo));
+            continue;
+        };
+        let before = &old[o];
+
+        if before.task != todo.task {
+            changes.push(Change::Retitled {
+                old: before,
+                new: todo,
+            });
+        }
+
+        match (before.completed, todo.completed) {
+            (false, true) => changes.push(Change::Completed(todo)),
+            (true, false) => changes.push(Change::Reopened(todo)),
+            _ => {}
+        }
+    }
+
+    changes.extend(
+        old.iter()
+            .zip(taken)
+            .filter(|(_, taken)| !taken)
+            .map(|(todo, _)| Change::Removed(todo)),
+    );
+
+    changes
+}
+
+/// Prints the changes from `other`, a CSV file or a named list, to the
+/// current database, one per line with a `+`, `-`, `x`, `o` or `~` marker.
+pub fn show(config: &Config, other: &str) {
+    let other_config = Config {
+        database: list_or_file(config, other),
+        ..config.clone()
+    };
+
+    let old: Vec<Todo> = get_reader(&other_config).deserialize().flatten().collect();
+    let new: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+    let changes = changes(&old, &new);
+
+    if changes.is_empty() {
+        println!("{}", i18n::text(config, "no-differences", &[]));
+        return;
+    }
+
+    let paint = |color: &str, line: String| match ansi_code(color) {
+        Some(code) if config.color => format!("{code}{line}{RESET}"),
+        _ => line,
+    };
+
+    let mut counts = [0; 5];
+
+    for change in &changes {
+        let line = match change {
+            Change::Added(todo) => {
+                counts[0] += 1;
+                paint("green", format!("+ {} {}", todo.id, todo.task))
+            }
+            Change::Removed(todo) => {
+                counts[1] += 1;
+                paint("red", format!("- {} {}", todo.id, todo.task))
+            }
+            Change::Completed(todo) => {
+                counts[2] += 1;
+                format!("x {} {}", todo.id, todo.task)
+            }
+            Change::Reopened(todo) => {
+                counts[3] += 1;
+                format!("o {} {}", todo.id, todo.task)
+            }
+            Change::Retitled { old, new } => {
+                counts[4] += 1;
+                paint(
+                    "yellow",
+                    format!("~ {} {:?} -> {:?}", new.id, old.task, new.task),
+                )
+            }
+        };
+
+        println!("{line}");
+    }
+
+    let [added, removed, completed, reopened, retitled] = counts;
+    println!(
+        "{}",
+        i18n::text(
+            config,
+            "diff-summary",
+            &[
+                ("added", &added),
+                ("removed", &removed),
+                ("completed", &completed),
+                ("reopened", &reopened),
+                ("retitled", &retitled),
+            ],
+        )
+    );
+}
@@ -0,0 +1,29 @@
+use chrono::TimeDelta;
+
+/// Parses a compact age such as `45m`, `12h`, `30d` or `2w`.
+pub fn parse_duration(input: &str) -> Option<TimeDelta> {
+    let input = input.trim();
+    let unit_start = input.find(|c: char| !c.is_ascii_digit())?;
+    let (amount, unit) = input.split_at(unit_start);
+    let amount: i64 = amount.parse().ok()?;
+
+    match unit {
+        "m" => TimeDelta::try_minutes(amount),
+        "h" => TimeDelta::try_hours(amount),
+        "d" => TimeDelta::try_days(amount),
+        "w" => TimeDelta::try_weeks(amount),
+        _ => None,
+    }
+}
+
+/// Formats an age in the same compact units, using the largest one that
+/// fits: `45m`, `12h`, `3d`, `2w`.
+pub fn format_duration(age: TimeDelta) -> String {
+    match age {
+        _ if age.num_minutes() < 1 => "<1m".to_owned(),
+        _ if age.num_hours() < 1 => format!("{}m", age.num_minutes()),
+        _ if age.num_days() < 1 => format!("{}h", age.num_hours()),
+        _ if age.num_weeks() < 1 => format!("{}d", age.num_days()),
+        _ => format!("{}w", age.num_weeks()),
+    }
+}
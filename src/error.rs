@@ -0,0 +1,316 @@
+use std::{fmt, process::exit};
+
+use serde::Serialize;
+
+use crate::{
+    COLUMNS,
+    config::{Config, OutputFormat, SETTINGS},
+    i18n,
+};
+
+/// Failures reported back to the user, in the language `i18n::activate`
+/// picked. With `--format json` they are written to stderr as
+/// `{"error":"<kind>", ...}` instead of a message.
+#[derive(Debug, Serialize)]
+#[serde(tag = "error", rename_all = "snake_case")]
+pub enum AppError {
+    InvalidCommand {
+        command: String,
+    },
+    MissingId,
+    InvalidId {
+        id: String,
+    },
+    NotFound {
+        id: String,
+    },
+    /// `NotFound` along with the IDs that do exist, for a likely typo.
+    #[serde(rename = "not_found")]
+    NotFoundNear {
+        id: String,
+        first: String,
+        last: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        closest: Option<String>,
+    },
+    NotCompleted {
+        id: String,
+    },
+    AlreadyCompleted {
+        id: String,
+    },
+    NothingCompleted,
+    NothingToUndo,
+    WrongPassphrase {
+        path: String,
+    },
+    MissingPassphrase {
+        path: String,
+    },
+    PassphraseMismatch,
+    TooManyActive {
+        active: usize,
+        limit: usize,
+    },
+    NoMatch {
+        text: String,
+    },
+    AmbiguousMatch {
+        text: String,
+        ids: Vec<String>,
+    },
+    OrderMismatch {
+        missing: Vec<String>,
+        unknown: Vec<String>,
+    },
+    HasSubtasks {
+        id: String,
+    },
+    InvalidAge {
+        age: String,
+    },
+    MissingAge,
+    IdOverflow,
+    EmptyDatabase,
+    NotWritable {
+        path: String,
+    },
+    NoDatabase {
+        path: String,
+    },
+    ReadOnly,
+    InvalidUtf8 {
+        path: String,
+    },
+    MalformedRow {
+        path: String,
+        line: Option<u64>,
+        reason: String,
+    },
+    MissingList,
+    MissingQuery,
+    MissingScript,
+    MissingDiff,
+    MissingMerge,
+    MissingMigrate,
+    MissingReorder,
+    MissingListInfo,
+    MissingGoal,
+    UnknownTemplate {
+        name: String,
+        known: Vec<String>,
+    },
+    NoSuchFile {
+        path: String,
+    },
+    MissingText,
+    NoInput,
+    InputTooLarge {
+        limit: usize,
+    },
+    MissingField,
+    InvalidField {
+        field: String,
+    },
+    InvalidList {
+        list: String,
+    },
+    SameList {
+        list: String,
+    },
+    SameDatabase {
+        path: String,
+    },
+    MissingSetting,
+    InvalidSetting {
+        setting: String,
+    },
+    InvalidValue {
+        value: String,
+        expected: String,
+    },
+    ReadInput {
+        reason: String,
+    },
+    ReadFailed {
+        path: String,
+        reason: String,
+    },
+    WriteFailed {
+        path: String,
+        reason: String,
+    },
+    AlreadyInRepl,
+    StaleHandle {
+        handle: String,
+    },
+    FlagInScript {
+        flag: String,
+    },
+    CommandInScript {
+        command: String,
+        known: Vec<String>,
+    },
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = i18n::message;
+        let usage = |usage: &str| text("error-usage", &[("usage", &usage)]);
+
+        let message = match self {
+            AppError::InvalidCommand { .. } => {
+                return write!(f, "{}", text("error-invalid-command", &[]));
+            }
+            AppError::MissingId => text("error-missing-id", &[]),
+            AppError::InvalidId { id } => text("error-invalid-id", &[("id", id)]),
+            AppError::NotFound { id } => text("error-not-found", &[("id", id)]),
+            AppError::NotFoundNear {
+                id,
+                first,
+                last,
+                closest,
+            } => {
+                let mut message = text(
+                    "error-not-found-near",
+                    &[("id", id), ("first", first), ("last", last)],
+                );
+
+                if let Some(closest) = closest {
+                    message += &text("error-did-you-mean", &[("closest", closest)]);
+                }
+
+                message
+            }
+            AppError::NotCompleted { id } => text("error-not-completed", &[("id", id)]),
+            AppError::AlreadyCompleted { id } => text("error-already-completed", &[("id", id)]),
+            AppError::NoMatch { text: query } => text("error-no-match", &[("text", query)]),
+            AppError::AmbiguousMatch { text: query, ids } => text(
+                "error-ambiguous-match",
+                &[("text", query), ("ids", &ids.join(", "))],
+            ),
+            AppError::OrderMismatch { missing, unknown } => {
+                let mut message = text("error-order-mismatch", &[]);
+
+                if !missing.is_empty() {
+                    message.push('\n');
+                    message += &text("error-order-missing", &[("ids", &missing.join(", "))]);
+                }
+
+                if !unknown.is_empty() {
+                    message.push('\n');
+                    message += &text("error-order-unknown", &[("ids", &unknown.join(", "))]);
+                }
+
+                message
+            }
+            AppError::NothingCompleted => text("error-nothing-completed", &[]),
+            AppError::NothingToUndo => text("error-nothing-to-undo", &[]),
+            AppError::WrongPassphrase { path } => text("error-wrong-passphrase", &[("path", path)]),
+            AppError::MissingPassphrase { path } => {
+                text("error-missing-passphrase", &[("path", path)])
+            }
+            AppError::PassphraseMismatch => text("error-passphrase-mismatch", &[]),
+            AppError::TooManyActive { active, limit } => text(
+                "error-too-many-active",
+                &[("active", active), ("limit", limit)],
+            ),
+            AppError::HasSubtasks { id } => text("error-has-subtasks", &[("id", id)]),
+            AppError::EmptyDatabase => text("error-empty-database", &[]),
+            AppError::IdOverflow => text("error-id-overflow", &[]),
+            AppError::NotWritable { path } => text("error-not-writable", &[("path", path)]),
+            AppError::NoDatabase { path } => text("error-no-database", &[("path", path)]),
+            AppError::ReadOnly => text("error-read-only", &[]),
+            AppError::InvalidUtf8 { path } => text("error-invalid-utf8", &[("path", path)]),
+            AppError::MalformedRow {
+                path,
+                line: Some(line),
+                reason,
+            } => text(
+                "error-malformed-line",
+                &[("path", path), ("line", line), ("reason", reason)],
+            ),
+            AppError::MalformedRow { path, reason, .. } => {
+                text("error-malformed", &[("path", path), ("reason", reason)])
+            }
+            AppError::MissingField => usage("todosh get <id> <field>"),
+            AppError::InvalidField { field } => {
+                let fields: Vec<String> = COLUMNS.iter().map(|c| c.to_ascii_lowercase()).collect();
+                text(
+                    "error-invalid-field",
+                    &[("field", field), ("fields", &fields.join(", "))],
+                )
+            }
+            AppError::MissingDiff => usage("todosh diff <file or list>"),
+            AppError::MissingMerge => usage("todosh merge <file or list>"),
+            AppError::MissingMigrate => usage("todosh migrate --to <csv|json>"),
+            AppError::MissingReorder => usage("todosh reorder --from <file>"),
+            AppError::NoSuchFile { path } => text("error-no-such-file", &[("path", path)]),
+            AppError::MissingListInfo => {
+                usage("todosh list-info set <name|description> <text> [--desc <text>]")
+            }
+            AppError::UnknownTemplate { name, known } if known.is_empty() => {
+                text("error-no-templates", &[("name", name)])
+            }
+            AppError::UnknownTemplate { name, known } => text(
+                "error-unknown-template",
+                &[("name", name), ("known", &known.join(", "))],
+            ),
+            AppError::MissingGoal => usage("todosh goal [set <count>]"),
+            AppError::MissingScript => usage("todosh run <file>"),
+            AppError::MissingText => text("error-missing-text", &[]),
+            AppError::NoInput => text("error-no-input", &[]),
+            AppError::InputTooLarge { limit } => text("error-input-too-large", &[("limit", limit)]),
+            AppError::MissingQuery => usage("todosh search <text>"),
+            AppError::MissingList => usage("todosh move-to <id> <list>"),
+            AppError::InvalidList { list } => text("error-invalid-list", &[("list", list)]),
+            AppError::SameList { list } => text("error-same-list", &[("list", list)]),
+            AppError::SameDatabase { path } => text("error-same-database", &[("path", path)]),
+            AppError::MissingSetting => usage("todosh set <setting> <value>"),
+            AppError::InvalidSetting { setting } => {
+                let names: Vec<&str> = SETTINGS.iter().map(|(name, ..)| *name).collect();
+                text(
+                    "error-invalid-setting",
+                    &[("setting", setting), ("names", &names.join(", "))],
+                )
+            }
+            AppError::InvalidValue { value, expected } => text(
+                "error-invalid-value",
+                &[("value", value), ("expected", expected)],
+            ),
+            AppError::MissingAge => usage("todosh expire --older-than <age> [--delete]"),
+            AppError::InvalidAge { age } => text("error-invalid-age", &[("age", age)]),
+            AppError::ReadInput { reason } => text("error-read-input", &[("reason", reason)]),
+            AppError::ReadFailed { path, reason } => {
+                text("error-read-failed", &[("path", path), ("reason", reason)])
+            }
+            AppError::WriteFailed { path, reason } => {
+                text("error-write-failed", &[("path", path), ("reason", reason)])
+            }
+            AppError::AlreadyInRepl => text("error-already-in-repl", &[]),
+            AppError::StaleHandle { handle } => text("error-stale-handle", &[("handle", handle)]),
+            AppError::FlagInScript { flag } => text("error-flag-in-script", &[("flag", flag)]),
+            AppError::CommandInScript { command, known } => text(
+                "error-command-in-script",
+                &[("command", command), ("known", &known.join(", "))],
+            ),
+        };
+
+        write!(f, "error: {message}")
+    }
+}
+
+impl AppError {
+    /// Reports the error in the configured output format and exits.
+    pub fn exit(self, config: &Config) -> ! {
+        match config.format {
+            OutputFormat::Json => match serde_json::to_string(&self) {
+                Ok(json) => eprintln!("{json}"),
+                Err(_) => eprintln!("{self}"),
+            },
+            OutputFormat::Table | OutputFormat::Html => println!("{self}"),
+        }
+
+        exit(1)
+    }
+}
@@ -0,0 +1,98 @@
+//! `--format html`: `list` and `export` as a standalone, styled HTML page
+//! for sharing where an ANSI table won't render.
+
+use std::fmt::Write;
+
+use crate::{Todo, color::PALETTE, config::Config, meta::ListMeta};
+
+static STYLE: &str = "\
+body { font-family: system-ui, sans-serif; margin: 2rem; color: #222; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.35rem 0.75rem; text-align: left; }
+th { background: #f3f3f3; }
+tr.done td.task { text-decoration: line-through; color: #888; }
+td.task { white-space: pre-wrap; }
+p.footer { color: #666; }";
+
+/// A whole page: the list's name as the title, then the table and footer.
+/// `todos` are already sorted and nested the way `list` shows them.
+pub fn render(config: &Config, todos: &[&Todo], footer: Option<&str>) -> String {
+    let meta = ListMeta::load(config).unwrap_or_default();
+    let title = escape(&meta.name_or_file(config));
+    let headers = &config.headers;
+
+    let mut page = String::new();
+    let _ = writeln!(page, "<!DOCTYPE html>");
+    let _ = writeln!(page, "<html>\n<head>\n<meta charset=\"utf-8\">");
+    let _ = writeln!(page, "<title>{title}</title>\n<style>\n{STYLE}\n</style>");
+    let _ = writeln!(page, "</head>\n<body>\n<h1>{title}</h1>");
+
+    if let Some(description) = &meta.description {
+        let _ = writeln!(page, "<p>{}</p>", escape(description));
+    }
+
+    let _ = writeln!(page, "<table>\n<thead>\n<tr>");
+    for label in [
+        headers.id.as_str(),
+        &headers.task,
+        &headers.completed,
+        "priority",
+        "tags",
+        &headers.due,
+    ] {
+        let _ = writeln!(page, "<th>{}</th>", escape(label));
+    }
+    let _ = writeln!(page, "</tr>\n</thead>\n<tbody>");
+
+    for todo in todos {
+        let class = if todo.completed {
+            " class=\"done\""
+        } else {
+            ""
+        };
+        // Only palette names reach the style attribute.
+        let style = match todo.color.as_str() {
+            color if PALETTE.contains(&color) => format!(" style=\"color: {color}\""),
+            _ => String::new(),
+        };
+        let due = todo.due.map(|due| due.to_string()).unwrap_or_default();
+
+        let _ = writeln!(
+            page,
+            "<tr{class}><td>{}</td><td class=\"task\"{style}>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            escape(&todo.id),
+            escape(&todo.task),
+            escape(config.completion_symbol(todo.completed)),
+            todo.priority,
+            escape(&todo.tags),
+            due,
+        );
+    }
+
+    let _ = writeln!(page, "</tbody>\n</table>");
+
+    if let Some(footer) = footer {
+        let _ = writeln!(page, "<p class=\"footer\">{}</p>", escape(footer));
+    }
+
+    page.push_str("</body>\n</html>");
+    page
+}
+
+/// Escapes text for use in element content and quoted attributes.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
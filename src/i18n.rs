@@ -0,0 +1,729 @@
+//! What the commands print, prompts and `AppError` messages included, in
+//! the language picked by `--lang`, `lang` in the config file or the locale
+//! (`LC_ALL`, `LC_MESSAGES`, `LANG`). Messages are looked up by key, with
+//! `{name}` placeholders, and fall back to English where a translation is
+//! missing. Table headers, `--porcelain` and `--json` output and the kinds
+//! in JSON errors stay in English for scripts, as do reports of a failed
+//! write or a broken pipe.
+
+use std::{cell::Cell, env, fmt::Display};
+
+use serde::Deserialize;
+
+use crate::config::Config;
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    #[default]
+    En,
+    De,
+}
+
+/// Codes `--lang` accepts.
+pub static LANGS: [&str; 2] = ["en", "de"];
+
+pub static EN: &[(&str, &str)] = &[
+    ("database-created", "Database created..."),
+    ("created-from", "Created {count} todos from {path}"),
+    ("updating", "Updating todo with id {id}..."),
+    ("logged", "Logged \"{task}\" as done, ID {id}"),
+    (
+        "missing-or-completed",
+        "Todo with ID '{id}' not found or already completed.",
+    ),
+    ("nothing-completed", "Nothing completed"),
+    (
+        "stays-open",
+        "Todo {id} stays open until its subtasks are done",
+    ),
+    ("completed-after", "Completed after {duration}"),
+    ("none-left", "Nice! Nothing left to do."),
+    ("one-left", "Nice! 1 task left."),
+    ("some-left", "Nice! {count} tasks left."),
+    ("reopened", "Reopened todo {id} \"{task}\""),
+    ("reopened-many", "Reopened todo(s) {ids}"),
+    (
+        "priority-unchanged",
+        "Todo {id} is already at {priority} priority",
+    ),
+    ("priority-changed", "Todo {id} priority: {old} -> {new}"),
+    ("deleted", "Deleted task \"{task}\" with ID \"{id}\""),
+    ("reordered", "Reordered {count} todo(s)"),
+    ("nothing-to-archive", "No completed todos to archive"),
+    ("archived", "Archived {count} todo(s) with ID {ids}"),
+    (
+        "archived-to",
+        "Archived {count} todo(s) with ID {ids} to {path}",
+    ),
+    ("archived-task-to", "Archived \"{task}\" to {path}"),
+    ("preview-only", "Preview only, nothing was saved"),
+    ("nothing-changed", "Nothing was changed"),
+    (
+        "complete-subtasks-too",
+        "Todo {id} has {open} open subtask(s). Complete them too? [y/N]",
+    ),
+    (
+        "moved",
+        "Moved todo {id} from {source} to {list} as ID {ids}",
+    ),
+    (
+        "nothing-to-expire",
+        "No incomplete todos created before {cutoff}",
+    ),
+    ("confirm-archive", "Archive these {count} todo(s)? [y/N]"),
+    ("confirm-delete", "Delete these {count} todo(s)? [y/N]"),
+    ("deleted-many", "Deleted {count} todo(s) with ID {ids}"),
+    (
+        "merged",
+        "Merged {merged} todo(s) from {other}, skipped {duplicates} duplicate(s), \
+         {conflicts} conflict(s) kept as completed",
+    ),
+    ("exported", "Exported {count} todo(s) to {path}"),
+    ("bulk-completed-none", "Completed 0 todos"),
+    ("bulk-completed", "Completed {count} todo(s): {ids}"),
+    (
+        "bulk-completed-skipped",
+        "Skipped {count} (not found or already completed): {ids}",
+    ),
+    ("bulk-deleted-none", "Deleted 0 todos"),
+    ("bulk-deleted", "Deleted {count} todo(s): {ids}"),
+    (
+        "bulk-deleted-skipped",
+        "Skipped {count} (not found or repeated): {ids}",
+    ),
+    ("normalized", "Normalized {count} cell(s) in {path}"),
+    ("already-stored-as", "{path} is already stored as {backend}"),
+    (
+        "already-exists",
+        "{path} already exists; move it away first",
+    ),
+    (
+        "migrated",
+        "Migrated {count} todo(s) to {path}\n\
+         Use it with backend = \"{backend}\" and database = \"{path}\" in data/config.toml",
+    ),
+    ("prompt-new-task", "Enter new task (press enter to submit):"),
+    (
+        "prompt-new-subtask",
+        "Enter new subtask (press enter to submit):",
+    ),
+    ("prompt-update", "Update todo ({task}):"),
+    (
+        "pick-which",
+        "Complete which one? [1-{count}, enter to cancel]",
+    ),
+    ("pick-number", "Enter a number from 1 to {count}"),
+    (
+        "no-templates",
+        "No templates yet; add them under [templates] in data/config.toml",
+    ),
+    ("no-goal", "No daily goal set; try `todosh goal set 5`"),
+    ("setting-saved", "{name} = {value}"),
+    ("expected-bool", "true or false"),
+    (
+        "expected-goal",
+        "a number of todos per day, or 0 for no goal",
+    ),
+    ("expected-list-info-field", "name or description"),
+    (
+        "pager-hint",
+        "hint: {lines} lines; set PAGER or pipe the output through less",
+    ),
+    ("progress", "{completed}/{total} completed ({percent}%)"),
+    ("goal-progress", "{done}/{goal} today [{bar}]"),
+    ("goal-reached", "{done}/{goal} today [{bar}] goal reached"),
+    ("times-completed", "Times completed: {count}"),
+    ("current-streak", "Current streak: {days} day(s)"),
+    ("longest-streak", "Longest streak: {days} day(s)"),
+    (
+        "completion-time",
+        "Time to complete: {average} average, {median} median ({count} todo(s))",
+    ),
+    (
+        "completion-time-none",
+        "Time to complete: no completed todos with both timestamps",
+    ),
+    ("workload", "Workload: {total} across {count} open todo(s)"),
+    ("workload-unestimated", ", {count} without an estimate"),
+    ("no-tags", "No tags yet"),
+    ("already-valid-utf8", "{path} is already valid UTF-8"),
+    (
+        "repaired",
+        "Repaired {path}: replaced {count} invalid character(s)",
+    ),
+    ("no-differences", "No differences"),
+    (
+        "diff-summary",
+        "{added} added, {removed} removed, {completed} completed, {reopened} reopened, \
+         {retitled} retitled",
+    ),
+    ("list-info-name", "name: {name}"),
+    ("list-info-description", "description: {description}"),
+    ("saved", "Saved {path}"),
+    (
+        "unreadable-meta",
+        "warning: Failed to parse {path}: {reason}",
+    ),
+    ("script-ok", "line {line}: ok, {outcome}"),
+    ("script-failed", "line {line}: {message}"),
+    ("script-summary", "{succeeded} succeeded, {failed} failed"),
+    ("script-created", "created {ids}"),
+    ("script-created-under", "created {id} under {parent}"),
+    ("script-completed", "completed {ids}"),
+    ("script-updated", "updated {id}"),
+    ("script-unchanged", "{id} already reads \"{text}\""),
+    ("script-deleted", "deleted {id} \"{task}\""),
+    ("ran", "{command} ran successfully"),
+    ("error-invalid-command", "Invalid command"),
+    ("error-usage", "Usage: {usage}"),
+    ("error-missing-id", "Id is expected"),
+    ("error-invalid-id", "Invalid Todo id supplied: {id}"),
+    ("error-not-found", "No Todo with ID {id}"),
+    (
+        "error-not-found-near",
+        "No Todo with ID {id}; IDs run from {first} to {last}",
+    ),
+    ("error-did-you-mean", ", did you mean {closest}?"),
+    ("error-not-completed", "Todo {id} is not completed"),
+    ("error-already-completed", "Todo {id} is already completed"),
+    ("error-no-match", "No pending todo matches '{text}'"),
+    (
+        "error-ambiguous-match",
+        "'{text}' matches todos {ids}; use an ID or more of the text",
+    ),
+    (
+        "error-order-mismatch",
+        "The order must list every todo exactly once",
+    ),
+    ("error-order-missing", "Missing: {ids}"),
+    ("error-order-unknown", "Unknown or repeated: {ids}"),
+    ("error-nothing-completed", "There are no completed todos"),
+    ("error-nothing-to-undo", "There is no completion to undo"),
+    (
+        "error-wrong-passphrase",
+        "Wrong passphrase for {path}, or the file was changed since it was saved",
+    ),
+    (
+        "error-missing-passphrase",
+        "{path} is encrypted; set TODOSH_PASSPHRASE or run on a terminal to enter it",
+    ),
+    (
+        "error-passphrase-mismatch",
+        "The passphrases don't match; nothing was saved",
+    ),
+    (
+        "error-too-many-active",
+        "{active} todos are already open and max_active is {limit}; \
+         complete or delete one first, or pass --force",
+    ),
+    (
+        "error-has-subtasks",
+        "Todo {id} is completed because all its subtasks are; reopen one of them",
+    ),
+    ("error-empty-database", "There are no todos yet"),
+    ("error-id-overflow", "The database has run out of todo IDs"),
+    (
+        "error-not-writable",
+        "Database directory is not writable: {path}\n\
+         Fix its permissions or set TODOSH_DB to a writable location",
+    ),
+    (
+        "error-no-database",
+        "Database {path} does not exist\n\
+         Check --db, --list and TODOSH_DB; a missing database is only created \
+         without --no-auto-create and --read-only",
+    ),
+    (
+        "error-read-only",
+        "Running with --read-only; nothing was changed",
+    ),
+    (
+        "error-invalid-utf8",
+        "{path} is not valid UTF-8; run `todosh repair` or set lossy_utf8 = true",
+    ),
+    ("error-malformed-line", "{path} line {line}: {reason}"),
+    ("error-malformed", "{path}: {reason}"),
+    (
+        "error-invalid-field",
+        "Unknown field '{field}', expected one of: {fields}",
+    ),
+    (
+        "error-no-such-file",
+        "'{path}' is neither a file nor a named list",
+    ),
+    (
+        "error-no-templates",
+        "No template '{name}'; add it under [templates] in data/config.toml",
+    ),
+    (
+        "error-unknown-template",
+        "No template '{name}', expected one of: {known}",
+    ),
+    ("error-missing-text", "Task text is expected"),
+    (
+        "error-no-input",
+        "No input provided; pass the text as an argument",
+    ),
+    (
+        "error-input-too-large",
+        "Input is longer than {limit} bytes; raise max_input_bytes to allow it",
+    ),
+    (
+        "error-invalid-list",
+        "Invalid list name '{list}', use letters, digits, '-' and '_'",
+    ),
+    ("error-same-list", "The todo is already in list '{list}'"),
+    ("error-same-database", "'{path}' is the database in use"),
+    (
+        "error-invalid-setting",
+        "Unknown setting '{setting}', expected one of: {names}",
+    ),
+    (
+        "error-invalid-value",
+        "Invalid value '{value}', expected {expected}",
+    ),
+    ("error-read-input", "Failed to read input: {reason}"),
+    ("error-read-failed", "Failed to read {path}: {reason}"),
+    ("error-write-failed", "Failed to write {path}: {reason}"),
+    ("error-already-in-repl", "Already in the REPL"),
+    (
+        "error-stale-handle",
+        "'{handle}' no longer refers to a todo",
+    ),
+    (
+        "error-flag-in-script",
+        "`--{flag}` can't be used in a script, which runs against one database",
+    ),
+    (
+        "error-command-in-script",
+        "`{command}` can't be used in a script, expected one of: {known}",
+    ),
+    (
+        "error-invalid-age",
+        "Invalid age '{age}', expected e.g. 30d, 12h or 2w",
+    ),
+];
+
+pub static DE: &[(&str, &str)] = &[
+    ("database-created", "Datenbank angelegt..."),
+    ("created-from", "{count} Todos aus {path} angelegt"),
+    ("updating", "Aktualisiere Todo mit ID {id}..."),
+    ("logged", "\"{task}\" als erledigt eingetragen, ID {id}"),
+    (
+        "missing-or-completed",
+        "Todo mit ID '{id}' nicht gefunden oder bereits erledigt.",
+    ),
+    ("nothing-completed", "Nichts erledigt"),
+    (
+        "stays-open",
+        "Todo {id} bleibt offen, bis seine Unteraufgaben erledigt sind",
+    ),
+    ("completed-after", "Erledigt nach {duration}"),
+    ("none-left", "Super! Nichts mehr zu tun."),
+    ("one-left", "Super! Noch 1 Aufgabe."),
+    ("some-left", "Super! Noch {count} Aufgaben."),
+    ("reopened", "Todo {id} \"{task}\" wieder geöffnet"),
+    ("reopened-many", "Todo(s) {ids} wieder geöffnet"),
+    (
+        "priority-unchanged",
+        "Todo {id} hat bereits die Priorität {priority}",
+    ),
+    ("priority-changed", "Todo {id} Priorität: {old} -> {new}"),
+    ("deleted", "Aufgabe \"{task}\" mit ID \"{id}\" gelöscht"),
+    ("reordered", "{count} Todo(s) neu geordnet"),
+    (
+        "nothing-to-archive",
+        "Keine erledigten Todos zum Archivieren",
+    ),
+    ("archived", "{count} Todo(s) mit ID {ids} archiviert"),
+    (
+        "archived-to",
+        "{count} Todo(s) mit ID {ids} nach {path} archiviert",
+    ),
+    ("archived-task-to", "\"{task}\" nach {path} archiviert"),
+    ("preview-only", "Nur Vorschau, nichts wurde gespeichert"),
+    ("nothing-changed", "Nichts wurde geändert"),
+    (
+        "complete-subtasks-too",
+        "Todo {id} hat {open} offene Unteraufgabe(n). Auch erledigen? [y/N]",
+    ),
+    (
+        "moved",
+        "Todo {id} von {source} nach {list} verschoben, neue ID {ids}",
+    ),
+    (
+        "nothing-to-expire",
+        "Keine offenen Todos, die vor {cutoff} angelegt wurden",
+    ),
+    (
+        "confirm-archive",
+        "Diese {count} Todo(s) archivieren? [y/N]",
+    ),
+    ("confirm-delete", "Diese {count} Todo(s) löschen? [y/N]"),
+    ("deleted-many", "{count} Todo(s) mit ID {ids} gelöscht"),
+    (
+        "merged",
+        "{merged} Todo(s) aus {other} übernommen, {duplicates} Duplikat(e) übersprungen, \
+         {conflicts} Konflikt(e) als erledigt behalten",
+    ),
+    ("exported", "{count} Todo(s) nach {path} exportiert"),
+    ("bulk-completed-none", "0 Todos erledigt"),
+    ("bulk-completed", "{count} Todo(s) erledigt: {ids}"),
+    (
+        "bulk-completed-skipped",
+        "{count} übersprungen (nicht gefunden oder bereits erledigt): {ids}",
+    ),
+    ("bulk-deleted-none", "0 Todos gelöscht"),
+    ("bulk-deleted", "{count} Todo(s) gelöscht: {ids}"),
+    (
+        "bulk-deleted-skipped",
+        "{count} übersprungen (nicht gefunden oder doppelt): {ids}",
+    ),
+    ("normalized", "{count} Zelle(n) in {path} normalisiert"),
+    (
+        "already-stored-as",
+        "{path} ist bereits als {backend} gespeichert",
+    ),
+    (
+        "already-exists",
+        "{path} existiert bereits; bitte zuerst verschieben",
+    ),
+    (
+        "migrated",
+        "{count} Todo(s) nach {path} umgezogen\n\
+         Zum Verwenden backend = \"{backend}\" und database = \"{path}\" in data/config.toml setzen",
+    ),
+    (
+        "prompt-new-task",
+        "Neue Aufgabe eingeben (mit Enter bestätigen):",
+    ),
+    (
+        "prompt-new-subtask",
+        "Neue Unteraufgabe eingeben (mit Enter bestätigen):",
+    ),
+    ("prompt-update", "Todo ändern ({task}):"),
+    (
+        "pick-which",
+        "Welches erledigen? [1-{count}, Enter zum Abbrechen]",
+    ),
+    ("pick-number", "Eine Zahl von 1 bis {count} eingeben"),
+    (
+        "no-templates",
+        "Noch keine Vorlagen; sie gehören unter [templates] in data/config.toml",
+    ),
+    (
+        "no-goal",
+        "Kein Tagesziel gesetzt; z. B. `todosh goal set 5`",
+    ),
+    ("setting-saved", "{name} = {value} gespeichert"),
+    ("expected-bool", "true oder false"),
+    (
+        "expected-goal",
+        "eine Anzahl Todos pro Tag, oder 0 für kein Ziel",
+    ),
+    ("expected-list-info-field", "name oder description"),
+    (
+        "pager-hint",
+        "Hinweis: {lines} Zeilen; PAGER setzen oder die Ausgabe durch less leiten",
+    ),
+    ("progress", "{completed}/{total} erledigt ({percent}%)"),
+    ("goal-progress", "{done}/{goal} heute [{bar}]"),
+    ("goal-reached", "{done}/{goal} heute [{bar}] Ziel erreicht"),
+    ("times-completed", "Wie oft erledigt: {count}"),
+    ("current-streak", "Aktuelle Serie: {days} Tag(e)"),
+    ("longest-streak", "Längste Serie: {days} Tag(e)"),
+    (
+        "completion-time",
+        "Zeit bis erledigt: {average} im Schnitt, {median} im Median ({count} Todo(s))",
+    ),
+    (
+        "completion-time-none",
+        "Zeit bis erledigt: keine erledigten Todos mit beiden Zeitstempeln",
+    ),
+    ("workload", "Aufwand: {total} über {count} offene Todo(s)"),
+    ("workload-unestimated", ", {count} ohne Schätzung"),
+    ("no-tags", "Noch keine Tags"),
+    ("already-valid-utf8", "{path} ist bereits gültiges UTF-8"),
+    (
+        "repaired",
+        "{path} repariert: {count} ungültige(s) Zeichen ersetzt",
+    ),
+    ("no-differences", "Keine Unterschiede"),
+    (
+        "diff-summary",
+        "{added} hinzugefügt, {removed} entfernt, {completed} erledigt, \
+         {reopened} wieder geöffnet, {retitled} umbenannt",
+    ),
+    ("list-info-name", "Name: {name}"),
+    ("list-info-description", "Beschreibung: {description}"),
+    ("saved", "{path} gespeichert"),
+    ("unreadable-meta", "warning: {path} nicht lesbar: {reason}"),
+    ("script-ok", "Zeile {line}: ok, {outcome}"),
+    ("script-failed", "Zeile {line}: {message}"),
+    (
+        "script-summary",
+        "{succeeded} erfolgreich, {failed} fehlgeschlagen",
+    ),
+    ("script-created", "{ids} angelegt"),
+    ("script-created-under", "{id} unter {parent} angelegt"),
+    ("script-completed", "{ids} erledigt"),
+    ("script-updated", "{id} geändert"),
+    ("script-unchanged", "{id} lautet bereits \"{text}\""),
+    ("script-deleted", "{id} \"{task}\" gelöscht"),
+    ("ran", "{command} erfolgreich ausgeführt"),
+    ("error-invalid-command", "Ungültiger Befehl"),
+    ("error-usage", "Aufruf: {usage}"),
+    ("error-missing-id", "Eine ID wird erwartet"),
+    ("error-invalid-id", "Ungültige Todo-ID angegeben: {id}"),
+    ("error-not-found", "Kein Todo mit ID {id}"),
+    (
+        "error-not-found-near",
+        "Kein Todo mit ID {id}; IDs gehen von {first} bis {last}",
+    ),
+    ("error-did-you-mean", ", meinten Sie {closest}?"),
+    ("error-not-completed", "Todo {id} ist nicht erledigt"),
+    ("error-already-completed", "Todo {id} ist bereits erledigt"),
+    ("error-no-match", "Kein offenes Todo passt zu '{text}'"),
+    (
+        "error-ambiguous-match",
+        "'{text}' passt zu den Todos {ids}; bitte eine ID oder mehr vom Text angeben",
+    ),
+    (
+        "error-order-mismatch",
+        "Die Reihenfolge muss jedes Todo genau einmal enthalten",
+    ),
+    ("error-order-missing", "Fehlend: {ids}"),
+    ("error-order-unknown", "Unbekannt oder doppelt: {ids}"),
+    ("error-nothing-completed", "Es gibt keine erledigten Todos"),
+    (
+        "error-nothing-to-undo",
+        "Es gibt keine Erledigung zum Rückgängigmachen",
+    ),
+    (
+        "error-wrong-passphrase",
+        "Falsche Passphrase für {path}, oder die Datei wurde seit dem Speichern geändert",
+    ),
+    (
+        "error-missing-passphrase",
+        "{path} ist verschlüsselt; TODOSH_PASSPHRASE setzen oder in einem Terminal \
+         ausführen, um sie einzugeben",
+    ),
+    (
+        "error-passphrase-mismatch",
+        "Die Passphrasen stimmen nicht überein; nichts wurde gespeichert",
+    ),
+    (
+        "error-too-many-active",
+        "{active} Todos sind bereits offen und max_active ist {limit}; \
+         zuerst eines erledigen oder löschen, oder --force angeben",
+    ),
+    (
+        "error-has-subtasks",
+        "Todo {id} ist erledigt, weil alle seine Unteraufgaben erledigt sind; \
+         eine davon wieder öffnen",
+    ),
+    ("error-empty-database", "Es gibt noch keine Todos"),
+    (
+        "error-id-overflow",
+        "Der Datenbank sind die Todo-IDs ausgegangen",
+    ),
+    (
+        "error-not-writable",
+        "Datenbankverzeichnis ist nicht beschreibbar: {path}\n\
+         Rechte korrigieren oder TODOSH_DB auf einen beschreibbaren Ort setzen",
+    ),
+    (
+        "error-no-database",
+        "Datenbank {path} existiert nicht\n\
+         --db, --list und TODOSH_DB prüfen; eine fehlende Datenbank wird nur ohne \
+         --no-auto-create und --read-only angelegt",
+    ),
+    (
+        "error-read-only",
+        "Läuft mit --read-only; nichts wurde geändert",
+    ),
+    (
+        "error-invalid-utf8",
+        "{path} ist kein gültiges UTF-8; `todosh repair` ausführen oder lossy_utf8 = true setzen",
+    ),
+    ("error-malformed-line", "{path} Zeile {line}: {reason}"),
+    ("error-malformed", "{path}: {reason}"),
+    (
+        "error-invalid-field",
+        "Unbekanntes Feld '{field}', erwartet wird eines von: {fields}",
+    ),
+    (
+        "error-no-such-file",
+        "'{path}' ist weder eine Datei noch eine benannte Liste",
+    ),
+    (
+        "error-no-templates",
+        "Keine Vorlage '{name}'; unter [templates] in data/config.toml anlegen",
+    ),
+    (
+        "error-unknown-template",
+        "Keine Vorlage '{name}', erwartet wird eine von: {known}",
+    ),
+    ("error-missing-text", "Aufgabentext wird erwartet"),
+    (
+        "error-no-input",
+        "Keine Eingabe; den Text als Argument übergeben",
+    ),
+    (
+        "error-input-too-large",
+        "Eingabe ist länger als {limit} Bytes; max_input_bytes erhöhen, um sie zu erlauben",
+    ),
+    (
+        "error-invalid-list",
+        "Ungültiger Listenname '{list}', erlaubt sind Buchstaben, Ziffern, '-' und '_'",
+    ),
+    (
+        "error-same-list",
+        "Das Todo ist bereits in der Liste '{list}'",
+    ),
+    (
+        "error-same-database",
+        "'{path}' ist die verwendete Datenbank",
+    ),
+    (
+        "error-invalid-setting",
+        "Unbekannte Einstellung '{setting}', erwartet wird eine von: {names}",
+    ),
+    (
+        "error-invalid-value",
+        "Ungültiger Wert '{value}', erwartet wird {expected}",
+    ),
+    ("error-read-input", "Eingabe nicht lesbar: {reason}"),
+    ("error-read-failed", "{path} nicht lesbar: {reason}"),
+    (
+        "error-write-failed",
+        "{path} konnte nicht geschrieben werden: {reason}",
+    ),
+    ("error-already-in-repl", "Bereits in der REPL"),
+    (
+        "error-stale-handle",
+        "'{handle}' verweist auf kein Todo mehr",
+    ),
+    (
+        "error-flag-in-script",
+        "`--{flag}` ist in einem Skript nicht erlaubt, es läuft gegen eine Datenbank",
+    ),
+    (
+        "error-command-in-script",
+        "`{command}` ist in einem Skript nicht erlaubt, erwartet wird einer von: {known}",
+    ),
+    (
+        "error-invalid-age",
+        "Ungültiges Alter '{age}', erwartet wird z. B. 30d, 12h oder 2w",
+    ),
+];
+
+impl Lang {
+    /// A language code or locale like `de`, `de-AT` or `de_DE.UTF-8`.
+    /// `C` and `POSIX` are English.
+    pub fn parse(value: &str) -> Option<Lang> {
+        let code = value.split(['_', '-', '.', '@']).next().unwrap_or_default();
+
+        match code.to_ascii_lowercase().as_str() {
+            "en" | "c" | "posix" => Some(Lang::En),
+            "de" => Some(Lang::De),
+            _ => None,
+        }
+    }
+
+    /// The locale's language, from the first of `LC_ALL`, `LC_MESSAGES`
+    /// and `LANG` that's set. Languages without a catalog are `None`.
+    pub fn from_env() -> Option<Lang> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Lang::parse(&value))
+    }
+
+    /// The code `--lang` and TODOSH_LANG take for this language.
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::De => "de",
+        }
+    }
+
+    fn catalog(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Lang::En => EN,
+            Lang::De => DE,
+        }
+    }
+}
+
+thread_local! {
+    /// The language `message` uses, since `AppError`'s `Display` has no
+    /// config to ask.
+    static ACTIVE: Cell<Option<Lang>> = const { Cell::new(None) };
+}
+
+/// Makes `lang` the language of `message` on this thread. `apply_args`
+/// calls it once the config's language is settled.
+pub fn activate(lang: Option<Lang>) {
+    ACTIVE.set(lang);
+}
+
+/// The message for `key` in the configured language, with each `{name}`
+/// replaced by its value in `args`.
+pub fn text(config: &Config, key: &str, args: &[(&str, &dyn Display)]) -> String {
+    localized(config.lang, key, args)
+}
+
+/// Like `text`, in the language last passed to `activate`.
+pub fn message(key: &str, args: &[(&str, &dyn Display)]) -> String {
+    localized(ACTIVE.get(), key, args)
+}
+
+fn localized(lang: Option<Lang>, key: &str, args: &[(&str, &dyn Display)]) -> String {
+    let lookup = |catalog: &[(&str, &'static str)]| {
+        catalog
+            .iter()
+            .find(|(name, _)| *name == key)
+            .map(|(_, template)| *template)
+    };
+    let template = lookup(lang.unwrap_or_default().catalog())
+        .or_else(|| lookup(EN))
+        .unwrap_or(key);
+
+    fill(template, args)
+}
+
+/// Substitutes placeholders in one pass, so a value that itself contains
+/// `{name}`, like a task's text, is left alone.
+pub fn fill(template: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let value = rest.find('}').and_then(|end| {
+            let name = &rest[1..end];
+            args.iter()
+                .find(|(arg, _)| *arg == name)
+                .map(|(_, value)| (end, value))
+        });
+
+        match value {
+            Some((end, value)) => {
+                filled.push_str(&value.to_string());
+                rest = &rest[end + 1..];
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    filled.push_str(rest);
+    filled
+}
@@ -0,0 +1,83 @@
+use std::{fs, path::Path};
+
+/// Patterns from an ignore file. Todos matching any of them are left out
+/// of `list` unless `--no-ignore` is passed.
+///
+/// A pattern containing `*` or `?` is a glob that has to match the whole
+/// task text or a whole tag; any other pattern matches wherever it appears
+/// in the task text or a tag. Matching ignores case.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreList {
+    patterns: Vec<String>,
+}
+
+impl IgnoreList {
+    /// Reads one pattern per line, skipping blank lines and `#` comments. A
+    /// missing file ignores nothing.
+    pub fn load(path: &Path) -> IgnoreList {
+        let contents = fs::read_to_string(path).unwrap_or_default();
+
+        IgnoreList {
+            patterns: contents
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_lowercase)
+                .collect(),
+        }
+    }
+
+    pub fn matches<'a>(&self, task: &str, tags: impl Iterator<Item = &'a str>) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+
+        let task = task.to_lowercase();
+        let tags: Vec<String> = tags.map(str::to_lowercase).collect();
+
+        self.patterns.iter().any(|pattern| {
+            let is_glob = pattern.contains(['*', '?']);
+
+            std::iter::once(&task).chain(&tags).any(|text| {
+                if is_glob {
+                    glob_match(pattern, text)
+                } else {
+                    text.contains(pattern.as_str())
+                }
+            })
+        })
+    }
+}
+
+/// Matches `*` (any run of characters) and `?` (one character) against the
+/// whole of `text`.
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where the last `*` was, and the text position it's matching up to.
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match star {
+                Some((star_p, star_t)) => {
+                    p = star_p + 1;
+                    t = star_t + 1;
+                    star = Some((star_p, star_t + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -1,383 +1,4288 @@
 use std::{
+    borrow::Cow,
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    env,
+    ffi::OsString,
     fs::{self, File, OpenOptions},
-    io::{self, Write},
-    path::Path,
-    process::{ExitCode, exit},
+    io::{self, BufRead, Cursor, IsTerminal, Read, Write},
+    path::{Path, PathBuf},
+    process::{self, ExitCode, exit},
 };
 
-use clap::{App, Arg};
+use chrono::{DateTime, Local, NaiveDate, SecondsFormat, TimeDelta, Utc};
+use clap::{App, AppSettings, Arg, ArgMatches};
 use csv::{Reader, ReaderBuilder, WriterBuilder};
-use serde::{Deserialize, Serialize};
-use tabled::{Table, Tabled, settings::Style};
+use serde::{Deserialize, Deserializer, Serialize, de::Error as _};
+use tabled::{
+    Table, Tabled,
+    builder::Builder,
+    settings::{Color, Style, Width, peaker::PriorityMax},
+};
+use unicode_width::UnicodeWidthStr;
+
+use color::{COLOR_MODES, PALETTE, RESET, ansi_code, color_enabled};
+use columns::COLUMNS;
+use config::{Backend, CompletedOrder, Config, GroupBy, OutputFormat, SearchField, SortBy};
+use duration::{format_duration, parse_duration};
+use error::AppError;
+use i18n::{LANGS, Lang};
+use ignore::IgnoreList;
+use priority::{PRIORITIES, Priority};
+use storage::storage;
+
+mod audit;
+mod batch;
+mod bulk;
+mod color;
+mod columns;
+mod config;
+mod diff;
+mod duration;
+mod error;
+mod html;
+mod i18n;
+mod ignore;
+mod merge;
+mod meta;
+mod priority;
+mod remember;
+mod repl;
+mod stats;
+mod storage;
+#[cfg(test)]
+mod tests;
+mod timezone;
+
+/// Todo IDs are 64-bit everywhere so large lists can't overflow them.
+type TodoId = u64;
+
+/// Position of the task in the rendered table, which `--color` tints.
+const TASK_COLUMN: usize = 1;
+
+/// Position of the completion state, shown with the configured symbols.
+const COMPLETED_COLUMN: usize = 2;
+
+#[derive(Debug, Serialize, Deserialize, Tabled, Clone, Default)]
+struct Todo {
+    #[serde(rename = "ID", deserialize_with = "deserialize_trimmed")]
+    id: String,
+    #[serde(rename = "TASK", deserialize_with = "deserialize_trimmed")]
+    task: String,
+    #[serde(rename = "COMPLETED", deserialize_with = "deserialize_completed")]
+    completed: bool,
+    #[serde(
+        rename = "COMPLETED_AT",
+        default,
+        deserialize_with = "deserialize_timestamp"
+    )]
+    #[tabled(skip)]
+    completed_at: Option<DateTime<Utc>>,
+    #[serde(rename = "NOTES", default, deserialize_with = "deserialize_trimmed")]
+    #[tabled(skip)]
+    notes: String,
+    /// Comma-separated list of tags.
+    #[serde(rename = "TAGS", default, deserialize_with = "deserialize_trimmed")]
+    #[tabled(skip)]
+    tags: String,
+    /// Display color for the task, one of `color::PALETTE`.
+    #[serde(rename = "COLOR", default, deserialize_with = "deserialize_trimmed")]
+    #[tabled(skip)]
+    color: String,
+    #[serde(
+        rename = "CREATED_AT",
+        default,
+        deserialize_with = "deserialize_timestamp"
+    )]
+    #[tabled(skip)]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(rename = "PRIORITY", default)]
+    #[tabled(skip)]
+    priority: Priority,
+    /// ID of the todo this is a subtask of, empty for top-level todos.
+    #[serde(
+        rename = "PARENT_ID",
+        default,
+        deserialize_with = "deserialize_trimmed"
+    )]
+    #[tabled(skip)]
+    parent_id: String,
+    /// Day the todo should be done by.
+    #[serde(rename = "DUE", default, deserialize_with = "deserialize_due")]
+    #[tabled(skip)]
+    due: Option<NaiveDate>,
+    /// Expected effort, in whatever unit the list uses (minutes, points).
+    #[serde(
+        rename = "ESTIMATE",
+        default,
+        deserialize_with = "deserialize_estimate"
+    )]
+    #[tabled(skip)]
+    estimate: Option<u32>,
+    /// How the todo was created: `cli`, `import`, `stdin`, `template`,
+    /// `batch` or `repl`. Empty for rows from before it was recorded.
+    #[serde(rename = "SOURCE", default, deserialize_with = "deserialize_trimmed")]
+    #[tabled(skip)]
+    source: String,
+    /// How many times the todo has been completed. Reopening takes a
+    /// completion back, so a todo done once stays at 0 or 1.
+    #[serde(
+        rename = "TIMES_COMPLETED",
+        default,
+        deserialize_with = "deserialize_count"
+    )]
+    #[tabled(skip)]
+    times_completed: u32,
+}
+
+impl Todo {
+    fn new(id: TodoId, task: &str) -> Todo {
+        Todo {
+            id: id.to_string(),
+            task: task.to_owned(),
+            completed: false,
+            completed_at: None,
+            notes: String::new(),
+            tags: String::new(),
+            color: String::new(),
+            created_at: Some(Utc::now()),
+            priority: Priority::default(),
+            parent_id: String::new(),
+            due: None,
+            estimate: None,
+            source: String::new(),
+            times_completed: 0,
+        }
+    }
+
+    /// Marks the todo done now and counts the completion.
+    fn complete(&mut self) {
+        self.completed = true;
+        self.completed_at = Some(Utc::now());
+        self.times_completed += 1;
+    }
+
+    /// Marks the todo pending again, taking back the completion it counted.
+    fn reopen(&mut self) {
+        self.completed = false;
+        self.completed_at = None;
+        self.times_completed = self.times_completed.saturating_sub(1);
+    }
+
+    /// How long the todo has been open: until now, or until it was
+    /// completed. `None` when the needed timestamps weren't recorded.
+    fn age(&self, now: DateTime<Utc>) -> Option<TimeDelta> {
+        let end = if self.completed {
+            self.completed_at?
+        } else {
+            now
+        };
+
+        Some(end - self.created_at?)
+    }
+
+    fn source_name(&self) -> &str {
+        match self.source.as_str() {
+            "" => "unknown",
+            source => source,
+        }
+    }
+
+    fn numeric_id(&self) -> TodoId {
+        self.id.parse().unwrap_or_default()
+    }
+
+    fn tag_list(&self) -> impl Iterator<Item = &str> {
+        self.tags
+            .split(',')
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+    }
+}
+
+/// Prints `message` and returns the line the user types, without the
+/// trailing newline. `None` when stdin is closed or redirected from an
+/// empty file, so there is no user to ask.
+fn prompt_line(config: &Config, message: &str) -> Option<String> {
+    println!("{message}");
+    read_answer(config, &mut io::stdin().lock())
+}
+
+/// Reads one line of `input`, or `None` at end of input. A line longer
+/// than `max_input_bytes` is an error.
+fn read_answer(config: &Config, input: &mut impl io::BufRead) -> Option<String> {
+    match read_line_capped(input, config.max_input_bytes) {
+        Ok(Line::End) => None,
+        Ok(Line::Text(line)) => Some(line.trim_end().to_owned()),
+        Ok(Line::TooLong) => AppError::InputTooLarge {
+            limit: config.max_input_bytes,
+        }
+        .exit(config),
+        Err(error) => AppError::ReadInput {
+            reason: error.to_string(),
+        }
+        .exit(config),
+    }
+}
+
+/// A line read by `read_line_capped`.
+enum Line {
+    Text(String),
+    TooLong,
+    End,
+}
+
+/// Reads one line of at most `limit` bytes, not counting its newline,
+/// without buffering more than that however long the line really is. The
+/// rest of a longer line is skipped so the next read starts after it.
+fn read_line_capped(input: &mut impl io::BufRead, limit: usize) -> io::Result<Line> {
+    let mut bytes = Vec::new();
+    let cap = u64::try_from(limit).unwrap_or(u64::MAX).saturating_add(1);
+
+    if input.by_ref().take(cap).read_until(b'\n', &mut bytes)? == 0 {
+        return Ok(Line::End);
+    }
+
+    if bytes.len() > limit && !bytes.ends_with(b"\n") {
+        input.skip_until(b'\n')?;
+        return Ok(Line::TooLong);
+    }
+
+    String::from_utf8(bytes)
+        .map(Line::Text)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Asks for task text, exiting with an error rather than storing an empty
+/// task when nothing is typed or there is no input to read.
+fn prompt_text(config: &Config, message: &str) -> String {
+    match prompt_line(config, message) {
+        None => AppError::NoInput.exit(config),
+        Some(text) if text.trim().is_empty() => AppError::MissingText.exit(config),
+        Some(text) => text,
+    }
+}
+
+/// Reads the spellings spreadsheets commonly use for booleans. Writing
+/// always uses `true`/`false`.
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" | "y" | "1" => Some(true),
+        "false" | "no" | "n" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Hand-edited files often pad fields after the delimiter (`1, Buy milk`).
+/// Only spaces and tabs are stripped: line breaks at either end of a quoted
+/// multi-line task or note are part of the value.
+fn trim_padding(value: &str) -> &str {
+    value.trim_matches([' ', '\t'])
+}
+
+fn deserialize_trimmed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let value = String::deserialize(deserializer)?;
+
+    Ok(trim_padding(&value).to_owned())
+}
+
+fn deserialize_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime<Utc>>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+
+    match value.trim() {
+        "" => Ok(None),
+        value => value
+            .parse()
+            .map(Some)
+            .map_err(|e| D::Error::custom(format!("invalid timestamp {value:?}: {e}"))),
+    }
+}
+
+fn deserialize_due<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<NaiveDate>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+
+    match value.trim() {
+        "" => Ok(None),
+        value => parse_due(value)
+            .map(Some)
+            .ok_or_else(|| D::Error::custom(format!("invalid DUE date {value:?}"))),
+    }
+}
+
+fn deserialize_estimate<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<u32>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+
+    match value.trim() {
+        "" => Ok(None),
+        value => value
+            .parse()
+            .map(Some)
+            .map_err(|_| D::Error::custom(format!("invalid ESTIMATE {value:?}"))),
+    }
+}
+
+/// A blank TIMES_COMPLETED cell counts as never completed.
+fn deserialize_count<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    let value = String::deserialize(deserializer)?;
+
+    match value.trim() {
+        "" => Ok(0),
+        value => value
+            .parse()
+            .map_err(|_| D::Error::custom(format!("invalid TIMES_COMPLETED {value:?}"))),
+    }
+}
+
+/// Due dates are written and read as `YYYY-MM-DD`.
+fn parse_due(value: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()
+}
+
+/// A blank COMPLETED cell, as left by a hand edit, reads as pending.
+fn deserialize_completed<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    let value = String::deserialize(deserializer)?;
+
+    if value.trim().is_empty() {
+        return Ok(false);
+    }
+
+    parse_bool(&value).ok_or_else(|| D::Error::custom(format!("invalid COMPLETED value {value:?}")))
+}
+
+/// A `daily_goal` value: a count of todos, 0 turning the goal off.
+fn parse_goal(value: &str) -> Option<u32> {
+    value.trim().parse().ok()
+}
+
+/// Layers the command-line flags over the settings from config and env.
+fn apply_args(config: &mut Config, args: &ArgMatches) {
+    config.only_completed = args.is_present("completed");
+    config.only_incomplete = (config.only_incomplete || args.is_present("only-incomplete"))
+        && !args.is_present("all")
+        && !config.only_completed;
+
+    if args.is_present("json") || args.value_of("format") == Some("json") {
+        config.format = OutputFormat::Json;
+    } else if args.value_of("format") == Some("table") {
+        config.format = OutputFormat::Table;
+    } else if args.value_of("format") == Some("html") {
+        config.format = OutputFormat::Html;
+    }
+
+    config.color = color_enabled(args.value_of("color"));
+    config.newest = args.is_present("newest");
+    config.utc = args.is_present("utc");
+    config.pretty = args.is_present("pretty");
+    // Output meant for `$(...)` can't carry notices.
+    config.quiet = args.is_present("quiet") || args.is_present("print-id");
+    config.audit |= args.is_present("audit");
+    config.show_age = args.is_present("show-age");
+    config.show_source = args.is_present("show-source");
+    config.tree = args.is_present("tree");
+    config.preview = args.is_present("preview");
+    config.strict |= args.is_present("strict");
+    config.read_only |= args.is_present("read-only");
+    config.force = args.is_present("force");
+
+    if let Some(lang) = args.value_of("lang").and_then(Lang::parse) {
+        config.lang = Some(lang);
+    }
+    i18n::activate(config.lang);
+
+    config.debug_layout = args.is_present("debug-layout");
+
+    if args.is_present("pager") {
+        config.pager = Some(true);
+    } else if args.is_present("no-pager") {
+        config.pager = Some(false);
+    }
+    config.auto_create &= !args.is_present("no-auto-create");
+    config.max_width = args.value_of("max-width").and_then(|n| n.parse().ok());
+
+    if let Some(symbol) = args.value_of("completed-symbol") {
+        config.completed_symbol = symbol.to_owned();
+    }
+
+    if let Some(symbol) = args.value_of("pending-symbol") {
+        config.pending_symbol = symbol.to_owned();
+    }
+
+    config.group_by = match args.value_of("group-by") {
+        Some("tag") => Some(GroupBy::Tag),
+        Some("completed") => Some(GroupBy::Completed),
+        Some("priority") => Some(GroupBy::Priority),
+        _ => None,
+    };
+    config.show_footer &= !args.is_present("no-footer");
+    config.sort = args
+        .value_of("sort")
+        .and_then(|spec| SortBy::parse_spec(spec).ok())
+        .unwrap_or_default();
+    config.nulls_first = args.value_of("nulls") == Some("first");
+
+    if args.is_present("completed-first") {
+        config.completed_order = CompletedOrder::First;
+    }
+
+    if let Some(backend) = args.value_of("backend").and_then(Backend::parse) {
+        config.backend = backend;
+        config.use_backend_extension();
+    }
+
+    if let Some(db) = args.value_of("db") {
+        config.database = PathBuf::from(db);
+    }
+
+    if let Some(list) = args.value_of("list") {
+        config.database = config.list_path(list).unwrap_or_else(|| {
+            AppError::InvalidList {
+                list: list.to_owned(),
+            }
+            .exit(config)
+        });
+    }
+
+    if args.is_present("archived") {
+        // The archive is always CSV.
+        config.database = config.archive_path();
+        config.backend = Backend::Csv;
+
+        // Read like a work log unless another order was asked for.
+        if config.sort.is_empty() {
+            config.sort = vec![SortBy::CompletedAt];
+        }
+    }
+
+    config.no_ignore |= args.is_present("no-ignore");
+
+    if !config.no_ignore {
+        config.ignore = IgnoreList::load(&config.ignore_path());
+    }
+}
+
+/// A new todo with the metadata given by `--tag`, `--color` and
+/// `--priority`, falling back to `default_priority` from config, created
+/// by `source`.
+fn todo_from_args(config: &Config, args: &ArgMatches, task: &str, source: &str) -> Todo {
+    Todo {
+        source: source.to_owned(),
+        tags: join_tags(args.values_of("tag").into_iter().flatten()),
+        color: args.value_of("task-color").unwrap_or_default().to_owned(),
+        priority: args
+            .value_of("priority")
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(config.default_priority),
+        due: args.value_of("due").and_then(parse_due),
+        estimate: args.value_of("estimate").and_then(|e| e.parse().ok()),
+        ..Todo::new(0, task)
+    }
+}
+
+/// `cli` for todos made by a command, unless TODOSH_SOURCE names where the
+/// command came from, as the REPL does with `repl`.
+fn cli_source() -> String {
+    env::var("TODOSH_SOURCE").unwrap_or_else(|_| "cli".to_owned())
+}
+
+/// Normalizes `--tag` values, which may repeat or hold comma-separated
+/// tags, into the stored comma-separated form.
+fn join_tags<'a>(values: impl IntoIterator<Item = &'a str>) -> String {
+    let mut tags: Vec<&str> = Vec::new();
+
+    for tag in values.into_iter().flat_map(|v| v.split(',')).map(str::trim) {
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+
+    tags.join(",")
+}
+
+fn cli() -> App<'static, 'static> {
+    App::new("todosh.rs")
+        .setting(AppSettings::AllowNegativeNumbers)
+        .version("1.0.0")
+        .about("Terminal based todo list app")
+        .arg(
+            Arg::with_name("command")
+                .help("The command to run (defaults to `default_command` from config)")
+                .takes_value(true)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("input")
+                .help("The input for the command; words after an ID form the task text")
+                .takes_value(true)
+                .multiple(true)
+                .required(false),
+        )
+        .arg(
+            Arg::with_name("db")
+                .long("db")
+                .value_name("PATH")
+                .help("Database file to use instead of data/db.csv (also TODOSH_DB)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("list")
+                .long("list")
+                .value_name("NAME")
+                .help("Use the named list NAME.csv next to the database")
+                .takes_value(true)
+                .conflicts_with("db"),
+        )
+        .arg(
+            Arg::with_name("no-auto-create")
+                .long("no-auto-create")
+                .help("Fail if the database doesn't exist instead of creating an empty one"),
+        )
+        .arg(
+            Arg::with_name("pager")
+                .long("pager")
+                .help("Show `list` through $PAGER on a terminal however short it is"),
+        )
+        .arg(
+            Arg::with_name("no-pager")
+                .long("no-pager")
+                .help("Never show `list` through $PAGER")
+                .conflicts_with("pager"),
+        )
+        .arg(
+            Arg::with_name("debug-layout")
+                .long("debug-layout")
+                .help("Print each `list` column's measured and rendered width to stderr")
+                .hidden(true),
+        )
+        .arg(
+            Arg::with_name("lang")
+                .long("lang")
+                .value_name("LANG")
+                .help("Language of command messages (default from LANG, else en)")
+                .possible_values(&LANGS)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("read-only")
+                .long("read-only")
+                .help("Refuse to run commands that would change the database, archive or config"),
+        )
+        .arg(
+            Arg::with_name("strict")
+                .long("strict")
+                .help("Fail on the first malformed database row instead of skipping it"),
+        )
+        .arg(
+            Arg::with_name("audit")
+                .long("audit")
+                .help("Record changes in audit.jsonl next to the database"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Output format for `list`, `show`, `export` and errors; `html` is a styled page for `list` and `export`")
+                .possible_values(&["table", "json", "html"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Shorthand for `--format json`"),
+        )
+        .arg(
+            Arg::with_name("format-cmd")
+                .long("format-cmd")
+                .value_name("PROGRAM")
+                .help("Pipe the `list` or `search` todos as JSON to PROGRAM and print its output")
+                .conflicts_with_all(&["porcelain", "count-only", "stream"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("print-id")
+                .long("print-id")
+                .help("Have `create` print only the new todo's ID instead of the list"),
+        )
+        .arg(
+            Arg::with_name("pretty")
+                .long("pretty")
+                .help("Indent JSON output from `list` and `show` for reading"),
+        )
+        .arg(
+            Arg::with_name("stream")
+                .long("stream")
+                .help("Render `list` row by row instead of loading every todo first"),
+        )
+        .arg(
+            Arg::with_name("only-incomplete")
+                .long("only-incomplete")
+                .help("Hide completed todos from the table"),
+        )
+        .arg(
+            Arg::with_name("completed")
+                .long("completed")
+                .conflicts_with_all(&["only-incomplete", "all"])
+                .help("Show only completed todos"),
+        )
+        .arg(Arg::with_name("all").long("all").help(
+            "Show completed and pending todos. `list` shows both unless \
+                     `hide_completed = true` is configured, which makes it pending-only",
+        ))
+        .arg(
+            Arg::with_name("default")
+                .long("default")
+                .help("Run `list` without, and forget, the filters `remember_list_filters` kept"),
+        )
+        .arg(
+            Arg::with_name("new")
+                .long("new")
+                .alias("since-last-run")
+                .help("List only todos created or completed since `list` last ran on this database"),
+        )
+        .arg(
+            Arg::with_name("completed-first")
+                .long("completed-first")
+                .help("List completed todos before pending ones, overriding `completed_order`"),
+        )
+        .arg(
+            Arg::with_name("utc")
+                .long("utc")
+                .help("Show timestamps as stored, in UTC, instead of the configured timezone"),
+        )
+        .arg(
+            Arg::with_name("archived")
+                .long("archived")
+                .help("`list` the todos moved to the archive instead of the active ones"),
+        )
+        .arg(
+            Arg::with_name("no-ignore")
+                .long("no-ignore")
+                .help("Show todos matching the ignore file next to the database"),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .short("q")
+                .long("quiet")
+                .help("Suppress extra output such as the list footer"),
+        )
+        .arg(
+            Arg::with_name("no-footer")
+                .long("no-footer")
+                .help("Don't print the completion summary under `list`"),
+        )
+        .arg(
+            Arg::with_name("group-by")
+                .long("group-by")
+                .value_name("FIELD")
+                .help("Split the `list` table into one table per tag or completion state")
+                .possible_values(&["tag", "completed", "priority"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tree")
+                .long("tree")
+                .help("Draw subtasks under their parents in `list` with ├─ and └─ connectors")
+                .conflicts_with("stream"),
+        )
+        .arg(
+            Arg::with_name("show-source")
+                .long("show-source")
+                .help("Add a `source` column to `list` with how each todo was created"),
+        )
+        .arg(
+            Arg::with_name("show-age")
+                .long("show-age")
+                .help("Add an `age` column to `list` with how long each todo has been open"),
+        )
+        .arg(
+            Arg::with_name("max-width")
+                .long("max-width")
+                .value_name("N")
+                .help("Wrap the `list` table to at most N columns in total")
+                .takes_value(true)
+                .validator(|v| match v.parse::<usize>() {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(format!("expected a number of columns, got {v:?}")),
+                }),
+        )
+        .arg(
+            Arg::with_name("completed-symbol")
+                .long("completed-symbol")
+                .value_name("TEXT")
+                .help("What `list` shows for completed todos, e.g. `[x]` or `done`")
+                .takes_value(true)
+                .validator(|v| config::validate_symbol(&v)),
+        )
+        .arg(
+            Arg::with_name("pending-symbol")
+                .long("pending-symbol")
+                .value_name("TEXT")
+                .help("What `list` shows for pending todos, e.g. `[ ]` or `todo`")
+                .takes_value(true)
+                .validator(|v| config::validate_symbol(&v)),
+        )
+        .arg(
+            Arg::with_name("porcelain")
+                .long("porcelain")
+                .conflicts_with_all(&["json", "format", "count-only"])
+                .help("`list` or `search` as stable tab-separated lines: id, completed, task"),
+        )
+        .arg(
+            Arg::with_name("field")
+                .long("field")
+                .value_name("FIELD")
+                .help("Where `search` looks: the task text, tags, notes or all of them (the default)")
+                .possible_values(&["task", "tags", "notes", "all"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("count-only")
+                .long("count-only")
+                .help("Print only how many todos `list` or `search` would show"),
+        )
+        .arg(
+            Arg::with_name("newest")
+                .long("newest")
+                .help("`list` the most recently created todos first"),
+        )
+        .arg(
+            Arg::with_name("top")
+                .long("top")
+                .help("`create` the todo at position 1 instead of the end of the list"),
+        )
+        .arg(
+            Arg::with_name("from")
+                .long("from")
+                .value_name("FILE")
+                .help("Order for `reorder`: one ID per line (`-` for stdin), e.g. edited `list --porcelain` output")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("input-file")
+                .long("input-file")
+                .value_name("FILE")
+                .help("`create` one todo per line of FILE (`-` for stdin), skipping blank and `#` lines")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("tag")
+                .long("tag")
+                .value_name("TAG")
+                .help("Tag the todo when running `create` or `log` (repeatable or comma-separated)")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("priority")
+                .long("priority")
+                .value_name("PRIORITY")
+                .help("Priority for `create` or `log` (defaults to `default_priority` from config)")
+                .possible_values(&PRIORITIES)
+                .case_insensitive(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("due")
+                .long("due")
+                .value_name("DATE")
+                .help("Due date for `create`, as YYYY-MM-DD")
+                .takes_value(true)
+                .validator(|v| match parse_due(&v) {
+                    Some(_) => Ok(()),
+                    None => Err(format!("expected a date like 2026-03-31, got {v:?}")),
+                }),
+        )
+        .arg(
+            Arg::with_name("estimate")
+                .long("estimate")
+                .value_name("EFFORT")
+                .help("Expected effort for `create`, a whole number of minutes or points; summed by `stats --workload`")
+                .takes_value(true)
+                .validator(|v| match v.parse::<u32>() {
+                    Ok(_) => Ok(()),
+                    Err(_) => Err(format!("expected a whole number, got {v:?}")),
+                }),
+        )
+        .arg(
+            Arg::with_name("sort")
+                .long("sort")
+                .value_name("FIELD")
+                .help(
+                    "Order `list` by comma-separated keys, e.g. `completed,created`: \
+                     completed, completed_at (newest first, the `--archived` default), \
+                     created, due, priority, id or task",
+                )
+                .takes_value(true)
+                .validator(|v| SortBy::parse_spec(&v).map(|_| ())),
+        )
+        .arg(
+            Arg::with_name("nulls")
+                .long("nulls")
+                .value_name("WHERE")
+                .help("Where `list --sort` puts todos missing a sorted date (default last)")
+                .possible_values(&["first", "last"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("color")
+                .long("color")
+                .value_name("WHEN")
+                .help(
+                    "Whether to color output: `always`, `auto` (when writing to a terminal, \
+                     the default) or `never`",
+                )
+                .possible_values(&COLOR_MODES)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("task-color")
+                .long("task-color")
+                .value_name("COLOR")
+                .help("Display color for the task when running `create` or `log`")
+                .possible_values(&PALETTE)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("backend")
+                .long("backend")
+                .value_name("FORMAT")
+                .help("How the database is stored (default csv, or TODOSH_BACKEND)")
+                .possible_values(&["csv", "json"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("to")
+                .long("to")
+                .value_name("FORMAT")
+                .help("Backend `migrate` converts the database to")
+                .possible_values(&["csv", "json"])
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("out")
+                .long("out")
+                .value_name("FILE")
+                .help("File `export` writes to instead of stdout")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("flat")
+                .long("flat")
+                .help("`export` as one line, `buy milk; [done] file taxes`, for commit messages and notes"),
+        )
+        .arg(
+            Arg::with_name("template")
+                .long("template")
+                .value_name("NAME")
+                .help("`create` with the task text of [templates] entry NAME from config")
+                .takes_value(true)
+                .conflicts_with("input-file"),
+        )
+        .arg(
+            Arg::with_name("desc")
+                .long("desc")
+                .value_name("TEXT")
+                .help("Description for `list-info set`, shown under the list's name")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("stdin")
+                .long("stdin")
+                .help("`complete` or `delete` every ID read from stdin, separated by whitespace, in one write")
+                .conflicts_with_all(&["match", "input"]),
+        )
+        .arg(
+            Arg::with_name("undo")
+                .long("undo")
+                .help("Reopen whatever the last `complete` completed")
+                .conflicts_with_all(&["match", "input", "stdin"]),
+        )
+        .arg(
+            Arg::with_name("match")
+                .long("match")
+                .value_name("TEXT")
+                .help("`complete` the pending todo whose text contains TEXT, picking from a list if several do")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("note")
+                .long("note")
+                .value_name("TEXT")
+                .help("Attach a note to the todo when running `complete` or `log`")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("preview")
+                .long("preview")
+                .help("Show the list as it would look after `complete`, without saving"),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .short("v")
+                .long("verbose")
+                .help("Print the task text when `exists` finds the todo"),
+        )
+        .arg(
+            Arg::with_name("streak")
+                .long("streak")
+                .help("Include current and longest daily completion streaks in `stats`"),
+        )
+        .arg(
+            Arg::with_name("avg-completion-time")
+                .long("avg-completion-time")
+                .help("Include how long completed todos were open, on average, in `stats`"),
+        )
+        .arg(
+            Arg::with_name("workload")
+                .long("workload")
+                .help("Include the summed estimates of incomplete todos in `stats`, only those with `--tag` if given"),
+        )
+        .arg(
+            Arg::with_name("by-tag")
+                .long("by-tag")
+                .help("Include completed/total per tag in `stats`, least complete first"),
+        )
+        .arg(
+            Arg::with_name("older-than")
+                .long("older-than")
+                .value_name("AGE")
+                .help(
+                    "Only `archive` todos completed longer ago than AGE (e.g. 30d, 12h, 2w); \
+                     `expire` and `list` todos created longer ago than AGE",
+                )
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("newer-than")
+                .long("newer-than")
+                .value_name("AGE")
+                .help("Only `list` todos created within AGE (e.g. 1d, 12h)")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("delete")
+                .long("delete")
+                .help("Have `expire` delete stale todos instead of archiving them"),
+        )
+        .arg(
+            Arg::with_name("yes")
+                .short("y")
+                .long("yes")
+                .help("Don't ask before `expire` removes todos"),
+        )
+        .arg(
+            Arg::with_name("force")
+                .long("force")
+                .help("Let `create` add todos past the `max_active` limit"),
+        )
+}
+
+fn main() -> ExitCode {
+    let mut config = Config::load();
+
+    let mut args = cli().get_matches();
+    let loaded = config.clone();
+
+    apply_args(&mut config, &args);
+
+    if config.remember_list_filters
+        && args.value_of("command").unwrap_or(&config.default_command) == "list"
+    {
+        let saved = remember::apply(&config, &args);
+
+        if !saved.is_empty()
+            && let Ok(with_saved) = cli()
+                .get_matches_from_safe(env::args_os().chain(saved.into_iter().map(OsString::from)))
+        {
+            args = with_saved;
+            config = loaded;
+            apply_args(&mut config, &args);
+        }
+    }
+    create_db_if_not_exists(&config);
+
+    if args.value_of("command").unwrap_or(&config.default_command) == "list" {
+        if args.is_present("new") {
+            config.since = remember::last_run(&config);
+        }
+
+        config.created_before = age_cutoff(&config, args.value_of("older-than"));
+        config.created_after = age_cutoff(&config, args.value_of("newer-than"));
+
+        if !config.read_only {
+            remember::record_run(&config, Utc::now());
+        }
+    }
+
+    let config = &config;
+    let pattern = args.value_of("command").unwrap_or(&config.default_command);
+
+    if !matches!(
+        pattern,
+        "create"
+            | "update"
+            | "delete"
+            | "list"
+            | "complete"
+            | "finish"
+            | "log"
+            | "uncomplete"
+            | "reopen"
+            | "archive"
+            | "exists"
+            | "show"
+            | "tags"
+            | "set"
+            | "add-sub"
+            | "path"
+            | "open"
+            | "stats"
+            | "move-to"
+            | "raise"
+            | "lower"
+            | "search"
+            | "repair"
+            | "normalize"
+            | "repl"
+            | "get"
+            | "run"
+            | "expire"
+            | "diff"
+            | "merge"
+            | "list-info"
+            | "goal"
+            | "templates"
+            | "export"
+            | "env"
+            | "migrate"
+            | "reorder"
+    ) {
+        AppError::InvalidCommand {
+            command: pattern.to_owned(),
+        }
+        .exit(config)
+    }
+
+    match pattern {
+        "list" if args.is_present("count-only") => println!("{}", count_listed(config)),
+        "list" if args.is_present("porcelain") => print_porcelain(config),
+        "list" if args.is_present("format-cmd") => {
+            run_formatter(config, args.value_of("format-cmd").unwrap_or_default())
+        }
+        "list"
+            if args.is_present("stream")
+                && config.format == OutputFormat::Table
+                && !config.newest
+                && !config.show_age
+                && !config.show_source
+                && config.max_width.is_none()
+                && config.group_by.is_none()
+                && config.sort.is_empty()
+                && config.completed_order == CompletedOrder::Mixed =>
+        {
+            stream_todos(config, &mut io::BufWriter::new(io::stdout().lock()))
+        }
+        "list" => list_paged(config),
+        "search" => {
+            let Some(query) = input_text(&args, 0) else {
+                AppError::MissingQuery.exit(config)
+            };
+
+            let config = &Config {
+                query: Some(query.to_lowercase()),
+                search_field: match args.value_of("field") {
+                    Some("task") => SearchField::Task,
+                    Some("tags") => SearchField::Tags,
+                    Some("notes") => SearchField::Notes,
+                    _ => SearchField::All,
+                },
+                ..config.clone()
+            };
+
+            if args.is_present("count-only") {
+                println!("{}", count_listed(config));
+            } else if args.is_present("porcelain") {
+                print_porcelain(config);
+            } else if let Some(program) = args.value_of("format-cmd") {
+                run_formatter(config, program);
+            } else {
+                list_todos(config);
+            }
+        }
+        "create" if args.is_present("input-file") => {
+            let path = args.value_of("input-file").unwrap_or_default();
+            let source = if path == "-" { "stdin" } else { "import" };
+            let todos: Vec<Todo> = read_task_file(config, path)
+                .iter()
+                .map(|task| todo_from_args(config, &args, task, source))
+                .collect();
+            let created = todos.len();
+
+            let ids = create_todos(config, todos, args.is_present("top"));
+
+            if args.is_present("print-id") {
+                println!("{}", ids.join("\n"));
+            } else {
+                println!(
+                    "{}",
+                    i18n::text(
+                        config,
+                        "created-from",
+                        &[("count", &created), ("path", &path)]
+                    )
+                );
+            }
+        }
+        "create" => {
+            let template = args
+                .value_of("template")
+                .map(|name| expand_template(config, name, Local::now().date_naive()));
+            let source = match template {
+                Some(_) => "template",
+                None => &cli_source(),
+            };
+
+            // Words given along with a template are added after it.
+            let input = match (template, input_text(&args, 0)) {
+                (Some(template), Some(words)) => format!("{template} {words}"),
+                (Some(template), None) => template,
+                (None, Some(words)) => words,
+                (None, None) => prompt_text(config, &i18n::text(config, "prompt-new-task", &[])),
+            };
+
+            let id = create_todo(
+                config,
+                todo_from_args(config, &args, &input, source),
+                args.is_present("top"),
+            );
+
+            if args.is_present("print-id") {
+                println!("{id}");
+            } else {
+                list_todos(config);
+            }
+        }
+        "log" => {
+            let Some(text) = input_text(&args, 0) else {
+                AppError::MissingText.exit(config)
+            };
+
+            let todo = todo_from_args(config, &args, &text, &cli_source());
+            let id = log_todo(config, todo, args.value_of("note"));
+
+            if args.is_present("print-id") {
+                println!("{id}");
+            } else {
+                println!(
+                    "{}",
+                    i18n::text(config, "logged", &[("task", &text), ("id", &id)])
+                );
+            }
+        }
+        "complete" if args.is_present("undo") => undo_completion(config),
+        "complete" if args.is_present("stdin") => {
+            return bulk::complete(config, &bulk::read_ids(config), args.value_of("note"));
+        }
+        "delete" if args.is_present("stdin") => {
+            return bulk::delete(config, &bulk::read_ids(config));
+        }
+        "complete" | "finish" => {
+            let value = match args.value_of("match") {
+                Some(text) => match match_pending(config, text) {
+                    Some(id) => Some(id),
+                    None => {
+                        println!("{}", i18n::text(config, "nothing-completed", &[]));
+                        return ExitCode::SUCCESS;
+                    }
+                },
+                None => args.value_of("input").map(str::to_owned),
+            };
+
+            if let Some(id) = value {
+                let id = resolve_id(config, &id);
+
+                let records: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+
+                if !records.iter().any(|todo| todo.id == id.to_string()) {
+                    not_found_in(&records, &id.to_string()).exit(config)
+                };
+
+                let open = subtask_ids(&records, &id.to_string())
+                    .iter()
+                    .filter(|sub| records.iter().any(|t| t.id == **sub && !t.completed))
+                    .count();
+
+                if open > 0
+                    && !prompt_line(
+                        config,
+                        &i18n::text(
+                            config,
+                            "complete-subtasks-too",
+                            &[("id", &id), ("open", &open)],
+                        ),
+                    )
+                    .and_then(|answer| parse_bool(&answer))
+                    .unwrap_or(false)
+                {
+                    println!("{}", i18n::text(config, "stays-open", &[("id", &id)]));
+                    return ExitCode::SUCCESS;
+                }
+
+                if pattern == "finish" {
+                    finish_todo(config, id.to_string(), args.value_of("note"));
+                } else {
+                    complete_todo(config, id.to_string(), args.value_of("note"));
+                }
+            } else {
+                AppError::MissingId.exit(config)
+            }
+        }
+        "uncomplete" | "reopen" => {
+            // `last` here means the most recently completed todo rather than
+            // the highest ID, and is what `reopen` does without an ID.
+            let id = match args.value_of("input") {
+                None if pattern == "uncomplete" => AppError::MissingId.exit(config),
+                None | Some("last") => None,
+                Some(value) => Some(resolve_id(config, value).to_string()),
+            };
+
+            reopen_todo(config, id);
+        }
+        "add-sub" => {
+            let Some(value) = args.value_of("input") else {
+                AppError::MissingId.exit(config)
+            };
+
+            let parent_id = resolve_id(config, value).to_string();
+
+            if !todo_exists(config, &parent_id) {
+                AppError::NotFound { id: parent_id }.exit(config)
+            };
+
+            let input = input_text(&args, 1).unwrap_or_else(|| {
+                prompt_text(config, &i18n::text(config, "prompt-new-subtask", &[]))
+            });
+
+            add_subtask(
+                config,
+                &parent_id,
+                todo_from_args(config, &args, &input, &cli_source()),
+            );
+            list_todos(config);
+        }
+        "move-to" => {
+            let mut input = args.values_of("input").into_iter().flatten();
+            let Some(value) = input.next() else {
+                AppError::MissingId.exit(config)
+            };
+            let Some(list) = input.next() else {
+                AppError::MissingList.exit(config)
+            };
+
+            let id = resolve_id(config, value).to_string();
+            move_todo(config, &id, list);
+        }
+        "raise" | "lower" => {
+            let Some(value) = args.value_of("input") else {
+                AppError::MissingId.exit(config)
+            };
+
+            let id = resolve_id(config, value).to_string();
+            shift_priority(config, &id, pattern == "raise");
+        }
+        "update" => {
+            let Some(value) = args.value_of("input") else {
+                AppError::MissingId.exit(config)
+            };
+
+            let id = resolve_id(config, value);
+
+            if !todo_exists(config, &id.to_string()) {
+                let records: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+                not_found_in(&records, &id.to_string()).exit(config)
+            };
+
+            update_todo(config, id.to_string(), input_text(&args, 1));
+        }
+        "delete" => {
+            let Some(value) = args.value_of("input") else {
+                AppError::MissingId.exit(config)
+            };
+
+            let id = resolve_id(config, value);
+
+            delete_todo(config, id.to_string())
+        }
+        "archive" => archive_todos(config, age_cutoff(config, args.value_of("older-than"))),
+        "expire" => {
+            let Some(age) = args.value_of("older-than") else {
+                AppError::MissingAge.exit(config)
+            };
+            let Some(older_than) = parse_duration(age) else {
+                AppError::InvalidAge {
+                    age: age.to_owned(),
+                }
+                .exit(config)
+            };
+
+            expire_todos(
+                config,
+                Utc::now() - older_than,
+                args.is_present("delete"),
+                args.is_present("yes"),
+            );
+        }
+        "show" => {
+            let Some(id) = args.value_of("input") else {
+                AppError::MissingId.exit(config)
+            };
+
+            match find_todo(config, id) {
+                Some(todo) => show_todo(config, &todo),
+                None => AppError::NotFound { id: id.to_owned() }.exit(config),
+            }
+        }
+        "tags" => list_tags(config),
+        "export" => export_todos(config, args.value_of("out"), args.is_present("flat")),
+        "templates" if config.templates.is_empty() => {
+            println!("{}", i18n::text(config, "no-templates", &[]))
+        }
+        "templates" => {
+            for (name, text) in &config.templates {
+                println!("{name} = {text}");
+            }
+        }
+        "get" => {
+            let mut input = args.values_of("input").into_iter().flatten();
+            let Some(value) = input.next() else {
+                AppError::MissingId.exit(config)
+            };
+            let Some(field) = input.next() else {
+                AppError::MissingField.exit(config)
+            };
+
+            let id = resolve_id(config, value).to_string();
+
+            match find_todo(config, &id) {
+                Some(todo) => println!("{}", field_value(config, &todo, field)),
+                None => AppError::NotFound { id }.exit(config),
+            }
+        }
+        "repair" => repair_database(config),
+        "normalize" => {
+            let changed = normalize_database(config);
+            println!(
+                "{}",
+                i18n::text(
+                    config,
+                    "normalized",
+                    &[("count", &changed), ("path", &config.database.display())],
+                )
+            );
+        }
+        "repl" => repl::run(config),
+        "reorder" => match args.value_of("from") {
+            Some(path) => reorder_todos(config, path),
+            None => AppError::MissingReorder.exit(config),
+        },
+        "migrate" => match args.value_of("to").and_then(Backend::parse) {
+            Some(to) => migrate_database(config, to),
+            None => AppError::MissingMigrate.exit(config),
+        },
+        "diff" => {
+            let Some(other) = args.value_of("input") else {
+                AppError::MissingDiff.exit(config)
+            };
+
+            diff::show(config, other);
+        }
+        "merge" => {
+            let Some(other) = args.value_of("input") else {
+                AppError::MissingMerge.exit(config)
+            };
+
+            merge::run(config, other);
+        }
+        "goal" => {
+            let input: Vec<&str> = args.values_of("input").into_iter().flatten().collect();
+
+            match input[..] {
+                [] => match goal_line(config) {
+                    Some(goal) => println!("{goal}"),
+                    None => println!("{}", i18n::text(config, "no-goal", &[])),
+                },
+                ["set", value] => {
+                    let Some(goal) = parse_goal(value) else {
+                        AppError::InvalidValue {
+                            value: value.to_owned(),
+                            expected: i18n::text(config, "expected-goal", &[]),
+                        }
+                        .exit(config)
+                    };
+
+                    ensure_writable(config);
+                    Config::save_setting("daily-goal", toml::Value::from(goal));
+                    println!(
+                        "{}",
+                        i18n::text(
+                            config,
+                            "setting-saved",
+                            &[("name", &"daily-goal"), ("value", &goal)],
+                        )
+                    );
+                }
+                _ => AppError::MissingGoal.exit(config),
+            }
+        }
+        "list-info" => {
+            let input: Vec<&str> = args.values_of("input").into_iter().flatten().collect();
+
+            return meta::run(config, &input, args.value_of("desc"));
+        }
+        "run" => {
+            let Some(path) = args.value_of("input") else {
+                AppError::MissingScript.exit(config)
+            };
+
+            return batch::run(config, path);
+        }
+        "stats" => {
+            show_stats(
+                config,
+                args.is_present("streak"),
+                args.is_present("avg-completion-time"),
+            );
+
+            if args.is_present("workload") {
+                let tags: Vec<&str> = args.values_of("tag").into_iter().flatten().collect();
+                show_workload(config, &tags);
+            }
+
+            if args.is_present("by-tag") {
+                show_tag_progress(config);
+            }
+        }
+        "path" => println!("{}", database_path(config).display()),
+        "env" => show_env(config),
+        "open" => open_database(config),
+        "set" => {
+            let mut input = args.values_of("input").into_iter().flatten();
+            let Some(name) = input.next() else {
+                AppError::MissingSetting.exit(config)
+            };
+
+            let (current, parsed, expected) = match name {
+                "hide-completed" => (
+                    config.only_incomplete.to_string(),
+                    input
+                        .clone()
+                        .next()
+                        .and_then(parse_bool)
+                        .map(toml::Value::from),
+                    i18n::text(config, "expected-bool", &[]),
+                ),
+                "default-priority" => (
+                    config.default_priority.to_string(),
+                    input
+                        .clone()
+                        .next()
+                        .and_then(|v| v.parse::<Priority>().ok())
+                        .map(|p| toml::Value::from(p.name())),
+                    PRIORITIES.join(", "),
+                ),
+                "daily-goal" => (
+                    config.daily_goal.to_string(),
+                    input
+                        .clone()
+                        .next()
+                        .and_then(parse_goal)
+                        .map(toml::Value::from),
+                    i18n::text(config, "expected-goal", &[]),
+                ),
+                _ => AppError::InvalidSetting {
+                    setting: name.to_owned(),
+                }
+                .exit(config),
+            };
+
+            // Without a value, show the current one.
+            let Some(value) = input.next() else {
+                println!("{name} = {current}");
+                return ExitCode::SUCCESS;
+            };
+
+            let Some(parsed) = parsed else {
+                AppError::InvalidValue {
+                    value: value.to_owned(),
+                    expected,
+                }
+                .exit(config)
+            };
+
+            ensure_writable(config);
+            Config::save_setting(name, parsed);
+            println!(
+                "{}",
+                i18n::text(
+                    config,
+                    "setting-saved",
+                    &[("name", &name), ("value", &value.to_ascii_lowercase())],
+                )
+            );
+        }
+        "exists" => {
+            let Some(id) = args.value_of("input") else {
+                AppError::MissingId.exit(config)
+            };
+
+            match find_todo(config, id) {
+                Some(todo) if args.is_present("verbose") => println!("{}", todo.task),
+                Some(_) => {}
+                None => return ExitCode::FAILURE,
+            }
+        }
+        _ => println!("{}", i18n::text(config, "ran", &[("command", &pattern)])),
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// The task text of template `name`, with `{date}` replaced by `today`.
+fn expand_template(config: &Config, name: &str, today: NaiveDate) -> String {
+    let Some(text) = config.templates.get(name) else {
+        AppError::UnknownTemplate {
+            name: name.to_owned(),
+            known: config.templates.keys().cloned().collect(),
+        }
+        .exit(config)
+    };
+
+    text.replace("{date}", &today.format("%Y-%m-%d").to_string())
+}
+
+/// Task texts for `create --input-file`: one per line, ignoring blank
+/// lines and `#` comments. `-` reads stdin, up to `max_input_bytes`.
+fn read_task_file(config: &Config, path: &str) -> Vec<String> {
+    let read = if path == "-" {
+        read_stdin_capped(config)
+    } else {
+        fs::read_to_string(path)
+    };
+
+    let contents = match read {
+        Ok(contents) => contents,
+        Err(e) => AppError::ReadFailed {
+            path: path.to_owned(),
+            reason: e.to_string(),
+        }
+        .exit(config),
+    };
+
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_owned)
+        .collect()
+}
+
+/// All of stdin, refusing it once it passes `max_input_bytes` rather than
+/// reading on.
+fn read_stdin_capped(config: &Config) -> io::Result<String> {
+    let limit = config.max_input_bytes;
+    let cap = u64::try_from(limit).unwrap_or(u64::MAX).saturating_add(1);
+    let mut contents = String::new();
+
+    io::stdin().lock().take(cap).read_to_string(&mut contents)?;
+
+    if contents.len() > limit {
+        AppError::InputTooLarge { limit }.exit(config)
+    }
+
+    Ok(contents)
+}
+
+/// The database path as an absolute path, after every override is applied.
+fn database_path(config: &Config) -> PathBuf {
+    fs::canonicalize(&config.database)
+        .or_else(|_| std::path::absolute(&config.database))
+        .unwrap_or_else(|_| config.database.clone())
+}
+
+/// `diff` and `merge` take a path to a CSV file, or else the name of a list
+/// next to the database.
+fn list_or_file(config: &Config, other: &str) -> PathBuf {
+    if Path::new(other).is_file() {
+        return PathBuf::from(other);
+    }
+
+    match config.list_path(other) {
+        Some(path) if path.is_file() => path,
+        _ => AppError::NoSuchFile {
+            path: other.to_owned(),
+        }
+        .exit(config),
+    }
+}
+
+/// Opens the database in `$VISUAL` or `$EDITOR`, falling back to the
+/// system's default application for the file.
+fn open_database(config: &Config) {
+    let path = database_path(config);
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(std::env::var_os)
+        .find(|editor| !editor.is_empty());
+
+    let mut command = match editor {
+        Some(editor) => process::Command::new(editor),
+        None if cfg!(target_os = "macos") => process::Command::new("open"),
+        None if cfg!(windows) => {
+            let mut command = process::Command::new("cmd");
+            command.args(["/C", "start", ""]);
+            command
+        }
+        None => process::Command::new("xdg-open"),
+    };
+
+    match command.arg(&path).status() {
+        Ok(status) if status.success() => {}
+        Ok(status) => {
+            println!("error: Opening {} exited with {status}", path.display());
+            exit(1);
+        }
+        Err(e) => {
+            println!("error: Failed to open {}: {e}", path.display());
+            exit(1);
+        }
+    }
+}
+
+/// Pipes the todos `list` would show, as a JSON array, to `program` run by
+/// the shell, and lets it print whatever it likes.
+fn run_formatter(config: &Config, program: &str) {
+    let mut todos = listed_todos(config);
+    sort_listed(config, &mut todos);
+    let json = to_json(config, &todos);
+
+    let mut child = match shell_command(program)
+        .stdin(process::Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            println!("error: Failed to run formatter `{program}`: {e}");
+            exit(1);
+        }
+    };
+
+    // A formatter that exits without reading all of its input is fine.
+    if let Some(mut stdin) = child.stdin.take()
+        && let Err(e) = stdin.write_all(json.as_bytes())
+        && e.kind() != io::ErrorKind::BrokenPipe
+    {
+        println!("error: Failed to send todos to formatter `{program}`: {e}");
+        exit(1);
+    }
+
+    match child.wait() {
+        Ok(status) if status.success() => {}
+        Ok(status) => {
+            println!("error: Formatter `{program}` exited with {status}");
+            exit(1);
+        }
+        Err(e) => {
+            println!("error: Failed to wait for formatter `{program}`: {e}");
+            exit(1);
+        }
+    }
+}
+
+/// Every write replaces the database with a new file created next to it,
+/// so the directory itself has to be writable, not just the file.
+fn dir_is_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".todosh-write-check.{}", process::id()));
+
+    match OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => fs::remove_file(&probe).is_ok(),
+        Err(_) => false,
+    }
+}
+
+fn create_db_if_not_exists(config: &Config) {
+    if (!config.auto_create || config.read_only) && !config.database.is_file() {
+        AppError::NoDatabase {
+            path: std::path::absolute(&config.database)
+                .unwrap_or_else(|_| config.database.clone())
+                .display()
+                .to_string(),
+        }
+        .exit(config)
+    }
+
+    // Reading needs neither a writable directory nor a new file.
+    if config.read_only {
+        return;
+    }
+
+    if let Some(dir) = config.database.parent()
+        && !dir.as_os_str().is_empty()
+        && !dir.exists()
+        && let Err(e) = fs::create_dir_all(dir)
+    {
+        eprintln!("Failed to create database directory: {e}");
+        exit(1);
+    }
+
+    let dir = match config.database.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    if !dir_is_writable(dir) {
+        let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+        AppError::NotWritable {
+            path: dir.display().to_string(),
+        }
+        .exit(config)
+    }
+
+    let db_exists = config.database.is_file();
+
+    if db_exists {
+        return;
+    }
+
+    match File::create(&config.database) {
+        Ok(_) if config.quiet => {}
+        Ok(_) => {
+            println!("{}", i18n::text(config, "database-created", &[]))
+        }
+        Err(e) => {
+            eprintln!("Failed to create database: {e:?}");
+            exit(1);
+        }
+    }
+}
+
+/// Reads the whole database into memory before parsing, so a concurrent
+/// write can't change the file halfway through the read.
+fn get_reader(config: &Config) -> Reader<Cursor<Vec<u8>>> {
+    let contents = match fs::read(&config.database) {
+        Ok(contents) => storage::decrypt(config, contents).unwrap_or_else(|e| e.exit(config)),
+        Err(e) => {
+            eprintln!("Failed to create csv reader: {e:?}");
+            exit(1);
+        }
+    };
+
+    let contents = match String::from_utf8(contents) {
+        Ok(text) => text.into_bytes(),
+        Err(e) if config.lossy_utf8 => {
+            eprintln!(
+                "warning: {} contains invalid UTF-8, run `todosh repair` to fix it",
+                config.database.display()
+            );
+            String::from_utf8_lossy(e.as_bytes())
+                .into_owned()
+                .into_bytes()
+        }
+        Err(_) => AppError::InvalidUtf8 {
+            path: config.database.display().to_string(),
+        }
+        .exit(config),
+    };
+
+    let contents = as_csv(config, contents);
+
+    if config.strict {
+        ensure_well_formed(config, csv_reader(config, Cursor::new(&contents)));
+    }
+
+    csv_reader(config, Cursor::new(contents))
+}
+
+/// The database's contents in CSV, whatever backend stores it.
+fn as_csv(config: &Config, contents: Vec<u8>) -> Vec<u8> {
+    storage(config)
+        .to_csv(config, contents)
+        .unwrap_or_else(|e| e.exit(config))
+}
+
+/// Reads rows straight from the file for `list --stream`, which must not
+/// hold the whole database in memory. Writes replace the file rather than
+/// rewriting it, so the open handle still sees one consistent version.
+/// Only plain CSV can be streamed; other backends and encrypted files are
+/// read whole.
+fn get_file_reader(config: &Config) -> Reader<Box<dyn io::Read>> {
+    if config.backend != Backend::Csv || storage::is_encrypted(&config.database) {
+        let contents = get_reader(config).into_inner().into_inner();
+        return csv_reader(config, Box::new(Cursor::new(contents)));
+    }
+
+    let open = || match File::open(&config.database) {
+        Ok(file) => csv_reader(config, Box::new(file) as Box<dyn io::Read>),
+        Err(e) => {
+            eprintln!("Failed to create csv reader: {e:?}");
+            exit(1);
+        }
+    };
+
+    // A separate pass, so the check streams too.
+    if config.strict {
+        ensure_well_formed(config, open());
+    }
+
+    open()
+}
+
+/// With `--strict`, exits at the first row that doesn't read as a todo,
+/// where every other reader skips it.
+fn ensure_well_formed<R: io::Read>(config: &Config, reader: Reader<R>) {
+    if let Some(e) = first_malformed(config, reader) {
+        e.exit(config)
+    }
+}
+
+/// Every todo, for a command that writes them all back. A row that doesn't
+/// parse is an error here rather than skipped, since the write would drop it.
+fn records_to_rewrite(config: &Config) -> Result<Vec<Todo>, AppError> {
+    match first_malformed(config, get_reader(config)) {
+        Some(e) => Err(e),
+        None => Ok(get_reader(config).deserialize().flatten().collect()),
+    }
+}
+
+/// The first row that doesn't read as a todo, with its line number.
+fn first_malformed<R: io::Read>(config: &Config, mut reader: Reader<R>) -> Option<AppError> {
+    let e = reader.deserialize::<Todo>().find_map(Result::err)?;
+
+    let reason = match e.kind() {
+        csv::ErrorKind::Deserialize { err, .. } => err.to_string(),
+        csv::ErrorKind::UnequalLengths {
+            expected_len, len, ..
+        } => format!("found {len} field(s), expected {expected_len}"),
+        _ => e.to_string(),
+    };
+
+    Some(AppError::MalformedRow {
+        path: config.database.display().to_string(),
+        line: e.position().map(|pos| pos.line()),
+        reason,
+    })
+}
+
+fn csv_reader<R: io::Read>(config: &Config, source: R) -> Reader<R> {
+    let mut reader = ReaderBuilder::new()
+        .delimiter(config.delimiter)
+        .trim(csv::Trim::Headers)
+        .from_reader(source);
+
+    if let Ok(headers) = reader.headers()
+        && let Some(e) = delimiter_mismatch(config, headers)
+    {
+        e.exit(config)
+    }
+
+    reader
+}
+
+/// A header that doesn't split on the configured delimiter means the file
+/// was written with another one; reading it as a single column, or
+/// rewriting it now, would mix both.
+fn delimiter_mismatch(config: &Config, headers: &csv::StringRecord) -> Option<AppError> {
+    if headers.len() != 1 {
+        return None;
+    }
+
+    let found = [b',', b';', b'\t', b'|']
+        .into_iter()
+        .find(|&d| d != config.delimiter && headers[0].as_bytes().contains(&d))?;
+
+    Some(AppError::MalformedRow {
+        path: config.database.display().to_string(),
+        line: Some(1),
+        reason: format!(
+            "appears to be delimited by {:?} but the configured delimiter is {:?}",
+            found as char, config.delimiter as char
+        ),
+    })
+}
+
+/// Joins the `input` words from position `skip` onwards, so unquoted
+/// multi-word tasks are kept intact.
+fn input_text(args: &ArgMatches, skip: usize) -> Option<String> {
+    let words: Vec<&str> = args.values_of("input")?.skip(skip).collect();
+
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+fn parse_id(config: &Config, value: &str) -> TodoId {
+    value.parse().unwrap_or_else(|_| {
+        AppError::InvalidId {
+            id: value.to_owned(),
+        }
+        .exit(config)
+    })
+}
+
+/// Like `parse_id`, but also accepts `last` for the highest ID and
+/// negative offsets from the end of the list: `-1` is the last todo, `-2`
+/// the one before it.
+fn resolve_id(config: &Config, value: &str) -> TodoId {
+    if value != "last" && !value.starts_with('-') {
+        return parse_id(config, value);
+    }
+
+    let records: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+
+    resolve_in(&records, value).unwrap_or_else(|e| e.exit(config))
+}
+
+/// The ID of the pending todo whose text contains `text`, ignoring case,
+/// for `complete --match`. Several matches are offered as a numbered list
+/// on a terminal, `None` meaning none was picked, and are an error when
+/// there is no one to ask.
+fn match_pending(config: &Config, text: &str) -> Option<String> {
+    let records: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+    let needle = text.to_lowercase();
+    let candidates: Vec<&Todo> = records
+        .iter()
+        .filter(|todo| !todo.completed && todo.task.to_lowercase().contains(&needle))
+        .collect();
+
+    match candidates[..] {
+        [] => AppError::NoMatch {
+            text: text.to_owned(),
+        }
+        .exit(config),
+        [todo] => Some(todo.id.clone()),
+        _ if io::stdin().is_terminal() && io::stdout().is_terminal() => {
+            pick_todo(config, &mut io::stdin().lock(), &candidates)
+        }
+        _ => AppError::AmbiguousMatch {
+            text: text.to_owned(),
+            ids: candidates.iter().map(|todo| todo.id.clone()).collect(),
+        }
+        .exit(config),
+    }
+}
+
+/// Numbers `candidates` and reads a choice from `input` until it's valid.
+/// An empty answer cancels.
+fn pick_todo(config: &Config, input: &mut impl BufRead, candidates: &[&Todo]) -> Option<String> {
+    for (n, todo) in (1..).zip(candidates) {
+        println!("{n}) [{}] {}", todo.id, todo.task);
+    }
+
+    loop {
+        println!(
+            "{}",
+            i18n::text(config, "pick-which", &[("count", &candidates.len())])
+        );
+
+        let Some(answer) = read_answer(config, input) else {
+            AppError::NoInput.exit(config)
+        };
+
+        if answer.trim().is_empty() {
+            return None;
+        }
+
+        match answer.trim().parse::<usize>() {
+            Ok(n) if (1..=candidates.len()).contains(&n) => {
+                return Some(candidates[n - 1].id.clone());
+            }
+            _ => println!(
+                "{}",
+                i18n::text(config, "pick-number", &[("count", &candidates.len())])
+            ),
+        }
+    }
+}
+
+/// `resolve_id` against todos already in memory.
+fn resolve_in(records: &[Todo], value: &str) -> Result<TodoId, AppError> {
+    let offset = match value.strip_prefix('-') {
+        _ if value == "last" => 1,
+        Some(offset) => match offset.parse::<usize>() {
+            Ok(offset) if offset > 0 => offset,
+            _ => {
+                return Err(AppError::InvalidId {
+                    id: value.to_owned(),
+                });
+            }
+        },
+        None => {
+            return value.parse().map_err(|_| AppError::InvalidId {
+                id: value.to_owned(),
+            });
+        }
+    };
+
+    let mut ids: Vec<TodoId> = records.iter().map(Todo::numeric_id).collect();
+
+    if ids.is_empty() {
+        return Err(AppError::EmptyDatabase);
+    }
+
+    ids.sort_unstable();
+
+    match ids.len().checked_sub(offset) {
+        Some(idx) => Ok(ids[idx]),
+        None => Err(AppError::NotFound {
+            id: value.to_owned(),
+        }),
+    }
+}
+
+/// Not-found for `id` with the range of IDs that exist and, for a numeric
+/// `id`, the nearest one (the lower on a tie), e.g. `9` for `12`.
+fn not_found_in(records: &[Todo], id: &str) -> AppError {
+    let ids: Vec<TodoId> = records.iter().map(Todo::numeric_id).collect();
+
+    let (Some(first), Some(last)) = (ids.iter().min(), ids.iter().max()) else {
+        return AppError::NotFound { id: id.to_owned() };
+    };
+
+    let closest = id.parse::<TodoId>().ok().and_then(|wanted| {
+        ids.iter()
+            .min_by_key(|&&existing| (existing.abs_diff(wanted), existing))
+            .map(TodoId::to_string)
+    });
+
+    AppError::NotFoundNear {
+        id: id.to_owned(),
+        first: first.to_string(),
+        last: last.to_string(),
+        closest,
+    }
+}
+
+fn find_todo(config: &Config, id: &str) -> Option<Todo> {
+    get_reader(config)
+        .deserialize::<Todo>()
+        .flatten()
+        .find(|todo| todo.id == id)
+}
+
+fn todo_exists(config: &Config, id: &str) -> bool {
+    find_todo(config, id).is_some()
+}
+
+/// Yields the rows `list` should display. When only incomplete or only
+/// completed todos are wanted, the other rows are recognised from their raw
+/// COMPLETED cell and skipped without being deserialized. Todos matching the
+/// ignore file are left out, and with a `search` query only todos containing
+/// it in the searched field are kept.
+fn listed_rows<'r>(
+    config: &Config,
+    reader: &'r mut Reader<impl io::Read>,
+) -> impl Iterator<Item = csv::Result<Todo>> + 'r {
+    let headers = reader.headers().cloned().unwrap_or_default();
+    let completed_column = headers.iter().position(|h| h == "COMPLETED");
+    let filtered = config.only_incomplete || config.only_completed;
+    let (shows_completed, shows_pending) = (config.shows(true), config.shows(false));
+    let hidden = move |row: &csv::StringRecord| match completed_column
+        .and_then(|i| row.get(i))
+        .and_then(parse_bool)
+    {
+        Some(true) => !shows_completed,
+        _ => !shows_pending,
+    };
+    let ignore = config.ignore.clone();
+    let query = config.query.clone();
+    let search_field = config.search_field;
+    let since = config.since;
+    let (before, after) = (config.created_before, config.created_after);
+
+    reader
+        .records()
+        .filter_map(move |row| match row {
+            Ok(row) if filtered && hidden(&row) => None,
+            Ok(row) => Some(row.deserialize(Some(&headers))),
+            Err(e) => Some(Err(e)),
+        })
+        .filter(move |row: &csv::Result<Todo>| {
+            !matches!(row, Ok(todo) if ignore.matches(&todo.task, todo.tag_list()))
+        })
+        .filter(move |row| match (row, &query) {
+            (Ok(todo), Some(query)) => matches_query(todo, query, search_field),
+            _ => true,
+        })
+        .filter(move |row| match (row, since) {
+            (Ok(todo), Some(since)) => {
+                todo.created_at.is_some_and(|at| at > since)
+                    || todo.completed_at.is_some_and(|at| at > since)
+            }
+            _ => true,
+        })
+        .filter(move |row| match row {
+            Ok(todo) if before.is_some() || after.is_some() => todo
+                .created_at
+                .is_some_and(|at| before.is_none_or(|b| at < b) && after.is_none_or(|a| at > a)),
+            _ => true,
+        })
+}
+
+/// Whether `field` of `todo` contains the lowercased `query`.
+fn matches_query(todo: &Todo, query: &str, field: SearchField) -> bool {
+    let in_task = || todo.task.to_lowercase().contains(query);
+    let in_tags = || {
+        todo.tag_list()
+            .any(|tag| tag.to_lowercase().contains(query))
+    };
+    let in_notes = || todo.notes.to_lowercase().contains(query);
+
+    match field {
+        SearchField::Task => in_task(),
+        SearchField::Tags => in_tags(),
+        SearchField::Notes => in_notes(),
+        SearchField::All => in_task() || in_tags() || in_notes(),
+    }
+}
+
+fn count_listed(config: &Config) -> usize {
+    listed_rows(config, &mut get_reader(config)).count()
+}
+
+fn list_todos(config: &Config) {
+    print_list_header(config);
+    println!("{}", render_list(config));
+}
+
+/// Writes what `list` would show, in the `--format` chosen or as one
+/// `--flat` line, to `out` or else stdout. Files never get ANSI colors.
+fn export_todos(config: &Config, out: Option<&str>, flat: bool) {
+    let config = &Config {
+        color: config.color && out.is_none(),
+        ..config.clone()
+    };
+    let mut todos = listed_todos(config);
+    let count = todos.len();
+    sort_listed(config, &mut todos);
+
+    let rendered = if flat {
+        flat_line(&todos)
+    } else {
+        render_todos(config, todos)
+    };
+
+    let Some(out) = out else {
+        if !flat {
+            print_list_header(config);
+        }
+        println!("{rendered}");
+        return;
+    };
+
+    if let Err(e) = fs::write(out, rendered + "\n") {
+        println!("error: Failed to write {out}: {e}");
+        exit(1);
+    }
+
+    println!(
+        "{}",
+        i18n::text(config, "exported", &[("count", &count), ("path", &out)])
+    );
+}
+
+/// `export --flat`: every todo on one line, e.g.
+/// `buy milk; call dentist; [done] file taxes`.
+fn flat_line(todos: &[Todo]) -> String {
+    let items: Vec<String> = todos
+        .iter()
+        .map(|todo| {
+            let task = todo
+                .task
+                .split_whitespace()
+                .collect::<Vec<&str>>()
+                .join(" ");
+            if todo.completed {
+                format!("[done] {task}")
+            } else {
+                task
+            }
+        })
+        .collect();
+
+    items.join("; ")
+}
+
+/// The list's name and description from `list-info`, above a table.
+fn print_list_header(config: &Config) {
+    if let Some(header) = list_header(config) {
+        println!("{header}");
+    }
+}
+
+fn list_header(config: &Config) -> Option<String> {
+    meta::header(config).filter(|_| config.format == OutputFormat::Table)
+}
+
+/// `list` itself: like `list_todos`, but output longer than `max_rows`
+/// lines on a terminal, or any with `--pager`, goes through the pager.
+fn list_paged(config: &Config) {
+    let rendered = render_list(config);
+    let output = match list_header(config) {
+        Some(header) => format!("{header}\n{rendered}"),
+        None => rendered,
+    };
+    let lines = output.lines().count();
+
+    if wants_pager(config, lines) && io::stdout().is_terminal() {
+        if page(&output) {
+            return;
+        }
+
+        eprintln!("{}", i18n::text(config, "pager-hint", &[("lines", &lines)]));
+    }
+
+    println!("{output}");
+}
+
+fn wants_pager(config: &Config, lines: usize) -> bool {
+    config
+        .pager
+        .unwrap_or(config.max_rows > 0 && lines > config.max_rows)
+}
+
+/// Shows `text` in `$PAGER`, or `less` with git's `LESS=FRX` so short
+/// output still prints and exits. `false` if no pager could be run.
+fn page(text: &str) -> bool {
+    let pager = env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less".to_owned());
+
+    let mut command = shell_command(&pager);
+
+    if env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+
+    let Ok(mut child) = command.stdin(process::Stdio::piped()).spawn() else {
+        return false;
+    };
+
+    // Quitting the pager early closes its input, which is fine.
+    if let Some(mut stdin) = child.stdin.take() {
+        let _ = writeln!(stdin, "{text}");
+    }
+
+    // The shell exits 127 when the pager isn't installed.
+    matches!(child.wait(), Ok(status) if status.code() != Some(127))
+}
+
+/// `program` run through the platform's shell, so it may carry arguments.
+fn shell_command(program: &str) -> process::Command {
+    let mut command = if cfg!(windows) {
+        let mut command = process::Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = process::Command::new("sh");
+        command.arg("-c");
+        command
+    };
+
+    command.arg(program);
+    command
+}
+
+/// Builds the `list` output for the current database.
+fn render_list(config: &Config) -> String {
+    let mut todos = listed_todos(config);
+    sort_listed(config, &mut todos);
+
+    render_todos(config, todos)
+}
+
+/// The todos `list` shows, in file order.
+fn listed_todos(config: &Config) -> Vec<Todo> {
+    let mut reader = get_reader(config);
+    let mut table_data: Vec<Todo> = Vec::new();
+
+    for result in listed_rows(config, &mut reader) {
+        let record: Todo = match result {
+            Ok(row) => row,
+            Err(e) => {
+                println!("Failed to parse csv row: {e:?}");
+                Todo::default()
+            }
+        };
+        table_data.push(record);
+    }
+
+    table_data
+}
+
+/// Puts listed todos in ascending ID order, whatever order a hand edit left
+/// the rows in, then applies `--newest` and `--sort` on top, and finally
+/// groups them by `completed_order`.
+fn sort_listed(config: &Config, todos: &mut [Todo]) {
+    todos.sort_by_key(Todo::numeric_id);
+
+    if config.newest {
+        // Rows from before creation times were recorded sort after the rest,
+        // newest ID first.
+        todos.sort_by_key(|todo| Reverse((todo.created_at, todo.numeric_id())));
+    }
+
+    if !config.sort.is_empty() {
+        // A stable sort, so todos equal on every key keep their order.
+        todos.sort_by(|a, b| {
+            config
+                .sort
+                .iter()
+                .map(|&key| compare_by(key, a, b, config.nulls_first))
+                .find(|order| order.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+    }
+
+    // Stable, so each group keeps the order above.
+    match config.completed_order {
+        CompletedOrder::Mixed => {}
+        CompletedOrder::First => todos.sort_by_key(|todo| !todo.completed),
+        CompletedOrder::Last => todos.sort_by_key(|todo| todo.completed),
+    }
+}
+
+/// Orders two todos on one `--sort` key. Missing dates go after every
+/// date, or before them with `nulls_first`; that holds for completion
+/// times too, though they run newest first.
+fn compare_by(key: SortBy, a: &Todo, b: &Todo, nulls_first: bool) -> Ordering {
+    fn dates<T: Ord>(a: Option<T>, b: Option<T>, nulls_first: bool) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (None, None) => Ordering::Equal,
+            (None, Some(_)) if nulls_first => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(_), None) if nulls_first => Ordering::Greater,
+            (Some(_), None) => Ordering::Less,
+        }
+    }
+
+    match key {
+        SortBy::Completed => a.completed.cmp(&b.completed),
+        SortBy::CompletedAt => match (a.completed_at, b.completed_at) {
+            (Some(a), Some(b)) => b.cmp(&a),
+            (a, b) => dates(a, b, nulls_first),
+        },
+        SortBy::Created => dates(a.created_at, b.created_at, nulls_first),
+        SortBy::Due => dates(a.due, b.due, nulls_first),
+        SortBy::Priority => b.priority.cmp(&a.priority),
+        SortBy::Id => a.numeric_id().cmp(&b.numeric_id()),
+        SortBy::Task => a.task.to_lowercase().cmp(&b.task.to_lowercase()),
+    }
+}
+
+/// Renders already loaded rows, in the order `sort_listed` left them, the
+/// way `list` shows them.
+fn render_todos(config: &Config, table_data: Vec<Todo>) -> String {
+    if config.format == OutputFormat::Json {
+        return to_json(config, &table_data);
+    }
+
+    let table_data = nest_subtasks(table_data, config.tree);
+
+    let footer = (config.show_footer && !config.quiet).then(|| {
+        let completed = table_data.iter().filter(|todo| todo.completed).count();
+        let mut footer = progress_footer(config, completed, table_data.len());
+
+        if let Some(goal) = goal_line(config) {
+            footer.push('\n');
+            footer.push_str(&goal);
+        }
+
+        footer
+    });
+
+    if config.format == OutputFormat::Html {
+        let todos: Vec<&Todo> = table_data.iter().collect();
+        return html::render(config, &todos, footer.as_deref());
+    }
+
+    let mut output = match config.group_by {
+        Some(group_by) => group_todos(&table_data, group_by)
+            .into_iter()
+            .map(|(group, todos)| format!("{group}\n{}", render_table(config, &todos)))
+            .collect::<Vec<String>>()
+            .join("\n\n"),
+        None => render_table(config, &table_data.iter().collect::<Vec<&Todo>>()),
+    };
+
+    if let Some(footer) = footer {
+        output.push('\n');
+        output.push_str(&footer);
+    }
+
+    output
+}
+
+fn render_table(config: &Config, todos: &[&Todo]) -> String {
+    let now = Utc::now();
+    let ages: Vec<Option<TimeDelta>> = todos.iter().map(|todo| todo.age(now)).collect();
+
+    let mut builder = Table::builder(todos);
+    builder.remove_record(0);
+    builder.insert_record(0, config.headers.labels());
+
+    let symbols = todos
+        .iter()
+        .map(|todo| config.completion_symbol(todo.completed).to_owned());
+    builder.remove_column(COMPLETED_COLUMN);
+    builder.insert_column(
+        COMPLETED_COLUMN,
+        std::iter::once(config.headers.completed.clone()).chain(symbols),
+    );
+
+    // The REPL's short handles go in front of the ID.
+    let handle_column = !config.handles.is_empty();
+
+    if handle_column {
+        let cells = todos
+            .iter()
+            .map(|todo| config.handles.get(&todo.id).cloned().unwrap_or_default());
+        builder.insert_column(0, std::iter::once(String::new()).chain(cells));
+    }
+
+    // Old rows without timestamps get an empty cell; the column is left out
+    // entirely if no row has an age.
+    if config.show_age && ages.iter().any(Option::is_some) {
+        let cells = ages
+            .iter()
+            .map(|age| age.map(format_duration).unwrap_or_default());
+        builder.push_column(std::iter::once(config.headers.age.clone()).chain(cells));
+    }
+
+    if config.show_source {
+        let cells = todos.iter().map(|todo| todo.source_name().to_owned());
+        builder.push_column(std::iter::once(config.headers.source.clone()).chain(cells));
+    }
+
+    if config.sort.contains(&SortBy::Due) {
+        let cells = todos
+            .iter()
+            .map(|todo| todo.due.map(|due| due.to_string()).unwrap_or_default());
+        builder.push_column(std::iter::once(config.headers.due.clone()).chain(cells));
+    }
+
+    let mut table = builder.build();
+    table.with(Style::modern());
+    // Labels and widths before any wrapping.
+    let measured = config.debug_layout.then(|| {
+        let labels: Vec<String> = table
+            .get_records()
+            .first()
+            .map(|header| header.iter().map(|cell| cell.as_ref().to_owned()).collect())
+            .unwrap_or_default();
+        (labels, column_widths(&table))
+    });
+
+    if let Some(max_width) = config.max_width {
+        // Narrow the widest columns first so short ones like the ID stay whole.
+        table.with(
+            Width::wrap(max_width)
+                .keep_words(true)
+                .priority(PriorityMax::right()),
+        );
+    }
+
+    if let Some((labels, measured)) = measured {
+        print_layout(&labels, &measured, &column_widths(&table));
+    }
+
+    if config.color {
+        for (row, todo) in todos.iter().enumerate() {
+            if let Some(code) = ansi_code(&todo.color) {
+                let column = TASK_COLUMN + usize::from(handle_column);
+                table.modify((row + 1, column), Color::new(code, RESET));
+            }
+        }
+    }
+
+    match &config.query {
+        Some(query) if config.color => highlight_matches(
+            &table.to_string(),
+            query,
+            TASK_COLUMN + usize::from(handle_column),
+        ),
+        _ => table.to_string(),
+    }
+}
+
+/// Shows `search` matches in the task column in reverse video. This runs on
+/// the rendered table, so the escapes don't count towards column widths.
+fn highlight_matches(table: &str, query: &str, column: usize) -> String {
+    // The header row, the second line after the top border, gives the
+    // number of cells; `--tree` guides in a task add to it on other rows.
+    let columns = table
+        .lines()
+        .nth(1)
+        .map_or(0, |header| header.split('│').count());
+
+    let lines = table.lines().enumerate().map(|(i, line)| {
+        let mut cells: Vec<&str> = line.split('│').collect();
+
+        // Cells follow the border before the first column.
+        if i == 1 || cells.len() < columns || column + 1 >= columns {
+            return line.to_owned();
+        }
+
+        let task_end = column + 2 + cells.len() - columns;
+        let task = highlight(&cells[column + 1..task_end].join("│"), query);
+        let after = cells.split_off(task_end);
+        cells.truncate(column + 1);
+
+        let mut line = cells.join("│");
+        line.push('│');
+        line.push_str(&task);
+
+        for cell in after {
+            line.push('│');
+            line.push_str(cell);
+        }
+
+        line
+    });
+
+    lines.collect::<Vec<String>>().join("\n")
+}
+
+/// Wraps each case-insensitive occurrence of the lowercased `query` in
+/// `text` with reverse video.
+fn highlight(text: &str, query: &str) -> String {
+    let length = query.chars().count();
+    let mut highlighted = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while !rest.is_empty() {
+        let end = rest
+            .char_indices()
+            .nth(length)
+            .map_or(rest.len(), |(end, _)| end);
+
+        if length > 0 && rest[..end].to_lowercase() == query {
+            highlighted.push_str(&format!("\u{1b}[7m{}\u{1b}[27m", &rest[..end]));
+            rest = &rest[end..];
+        } else {
+            let next = rest.chars().next().map_or(1, char::len_utf8);
+            highlighted.push_str(&rest[..next]);
+            rest = &rest[next..];
+        }
+    }
+
+    highlighted
+}
+
+/// Splits todos into labelled groups in order of first appearance, keeping
+/// each group in list order. A todo with several tags joins every group.
+fn group_todos(todos: &[Todo], group_by: GroupBy) -> Vec<(String, Vec<&Todo>)> {
+    let mut groups: Vec<(String, Vec<&Todo>)> = Vec::new();
+    let mut untagged: Vec<&Todo> = Vec::new();
+
+    for todo in todos {
+        let keys: Vec<&str> = match group_by {
+            GroupBy::Tag => todo.tag_list().collect(),
+            GroupBy::Completed if todo.completed => vec!["completed"],
+            GroupBy::Completed => vec!["pending"],
+            GroupBy::Priority => vec![todo.priority.name()],
+        };
+
+        if keys.is_empty() {
+            untagged.push(todo);
+        }
+
+        for key in keys {
+            match groups.iter_mut().find(|(group, _)| group == key) {
+                Some((_, members)) => members.push(todo),
+                None => groups.push((key.to_owned(), vec![todo])),
+            }
+        }
+    }
+
+    if group_by == GroupBy::Priority {
+        groups.sort_by_key(|(group, _)| Reverse(group.parse::<Priority>().unwrap_or_default()));
+    }
+
+    if !untagged.is_empty() {
+        groups.push(("(untagged)".to_owned(), untagged));
+    }
+
+    groups
+}
+
+/// The `daily_goal` line under `list` and `stats`, counting completions
+/// since local midnight across the whole database, not just listed rows.
+fn goal_line(config: &Config) -> Option<String> {
+    if config.daily_goal == 0 {
+        return None;
+    }
+
+    let today = Local::now().date_naive();
+    let done = get_reader(config)
+        .deserialize::<Todo>()
+        .flatten()
+        .filter(|todo| todo.completed)
+        .filter_map(|todo| todo.completed_at)
+        .filter(|at| at.with_timezone(&Local).date_naive() == today)
+        .count();
+
+    Some(stats::goal_progress(config, done, config.daily_goal))
+}
+
+/// One-line summary such as `5/12 completed (42%)` shown under the table.
+fn progress_footer(config: &Config, completed: usize, total: usize) -> String {
+    i18n::text(
+        config,
+        "progress",
+        &[
+            ("completed", &completed),
+            ("total", &total),
+            ("percent", &percent(completed, total)),
+        ],
+    )
+}
+
+/// `completed` out of `total` as a rounded percentage, 0 when there are none.
+fn percent(completed: usize, total: usize) -> usize {
+    (completed * 100 + total / 2)
+        .checked_div(total)
+        .unwrap_or(0)
+}
+
+/// Compact JSON, or indented with `--pretty`.
+fn to_json<T: Serialize + ?Sized>(config: &Config, value: &T) -> String {
+    let json = if config.pretty {
+        serde_json::to_string_pretty(value)
+    } else {
+        serde_json::to_string(value)
+    };
+
+    match json {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("Failed to serialize todos: {e}");
+            exit(1);
+        }
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(config: &Config, value: &T) {
+    println!("{}", to_json(config, value));
+}
+
+/// Prints every field of a single todo, including ones hidden from `list`.
+fn show_todo(config: &Config, todo: &Todo) {
+    if config.format == OutputFormat::Json {
+        print_json(config, todo);
+        return;
+    }
+
+    let timestamp = |at: Option<DateTime<Utc>>| match at {
+        Some(at) if config.utc => at.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        Some(at) => config.timezone.render(at),
+        None => String::new(),
+    };
+
+    let mut builder = Builder::default();
+    builder.push_record(["ID", &todo.id]);
+    builder.push_record(["TASK", &todo.task]);
+    builder.push_record(["COMPLETED", &todo.completed.to_string()]);
+    builder.push_record(["CREATED AT", &timestamp(todo.created_at)]);
+    builder.push_record(["COMPLETED AT", &timestamp(todo.completed_at)]);
+    builder.push_record(["PARENT", &todo.parent_id]);
+    builder.push_record(["PRIORITY", todo.priority.name()]);
+    builder.push_record([
+        "DUE",
+        &todo.due.map(|due| due.to_string()).unwrap_or_default(),
+    ]);
+    builder.push_record([
+        "ESTIMATE",
+        &todo.estimate.map(|e| e.to_string()).unwrap_or_default(),
+    ]);
+    builder.push_record(["SOURCE", todo.source_name()]);
+    builder.push_record(["TIMES COMPLETED", &todo.times_completed.to_string()]);
+    builder.push_record(["TAGS", &todo.tags]);
+    builder.push_record(["COLOR", &todo.color]);
+    builder.push_record(["NOTES", &todo.notes]);
+
+    let mut table = builder.build();
+    table.with(Style::modern());
+
+    println!("{table}");
+}
+
+/// The settings in effect after `data/config.toml`, TODOSH_DB and flags,
+/// for working out why todosh behaves the way it does.
+fn show_env(config: &Config) {
+    let config_file = Config::file_path();
+    let config_path = std::path::absolute(config_file)
+        .unwrap_or_else(|_| config_file.to_path_buf())
+        .display()
+        .to_string();
+    let delimiter = match config.delimiter {
+        b'\t' => "tab".to_owned(),
+        byte => char::from(byte).to_string(),
+    };
+
+    let rows = [
+        ("database", database_path(config).display().to_string()),
+        match config_file.is_file() {
+            true => ("config", config_path),
+            false => ("config", format!("{config_path} (not found)")),
+        },
+        ("backend", config.backend.name().to_owned()),
+        ("color", if config.color { "on" } else { "off" }.to_owned()),
+        ("default_command", config.default_command.clone()),
+        ("delimiter", delimiter),
+    ];
+
+    if config.format == OutputFormat::Json {
+        let map: BTreeMap<&str, &str> = rows.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        print_json(config, &map);
+        return;
+    }
+
+    let mut builder = Builder::default();
+    for (name, value) in rows {
+        builder.push_record([name, &value]);
+    }
+
+    let mut table = builder.build();
+    table.with(Style::modern());
+
+    println!("{table}");
+}
+
+/// Prints how many todos are done, with `--streak` how many days in a row
+/// at least one was completed, and with `--avg-completion-time` how long
+/// completed todos stayed open.
+fn show_stats(config: &Config, with_streak: bool, with_completion_time: bool) {
+    let todos: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+    let completed = todos.iter().filter(|todo| todo.completed).count();
+
+    let times_completed: u64 = todos
+        .iter()
+        .map(|todo| u64::from(todo.times_completed))
+        .sum();
+
+    println!("{}", progress_footer(config, completed, todos.len()));
+    println!(
+        "{}",
+        i18n::text(config, "times-completed", &[("count", &times_completed)])
+    );
+
+    if let Some(goal) = goal_line(config) {
+        println!("{goal}");
+    }
+
+    if with_streak {
+        let days: BTreeSet<NaiveDate> = todos
+            .iter()
+            .filter_map(|todo| todo.completed_at)
+            .map(|at| at.with_timezone(&Local).date_naive())
+            .collect();
+        let (current, longest) = stats::streaks(&days, Local::now().date_naive());
+
+        println!(
+            "{}",
+            i18n::text(config, "current-streak", &[("days", &current)])
+        );
+        println!(
+            "{}",
+            i18n::text(config, "longest-streak", &[("days", &longest)])
+        );
+    }
+
+    if with_completion_time {
+        // `age` of a completed todo runs from creation to completion, and is
+        // `None` when either timestamp is missing.
+        let now = Utc::now();
+        let mut durations: Vec<TimeDelta> = todos
+            .iter()
+            .filter(|todo| todo.completed)
+            .filter_map(|todo| todo.age(now))
+            .collect();
+
+        match stats::average_and_median(&mut durations) {
+            Some((average, median)) => println!(
+                "{}",
+                i18n::text(
+                    config,
+                    "completion-time",
+                    &[
+                        ("average", &format_duration(average)),
+                        ("median", &format_duration(median)),
+                        ("count", &durations.len()),
+                    ],
+                )
+            ),
+            None => println!("{}", i18n::text(config, "completion-time-none", &[])),
+        }
+    }
+}
+
+/// `stats --workload`: the outstanding effort, summing the estimates of
+/// incomplete todos that have any of `tags`, or of all of them.
+fn show_workload(config: &Config, tags: &[&str]) {
+    let todos: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+    let tags: Vec<&str> = tags
+        .iter()
+        .flat_map(|t| t.split(','))
+        .map(str::trim)
+        .collect();
+    let open: Vec<&Todo> = todos
+        .iter()
+        .filter(|todo| !todo.completed)
+        .filter(|todo| tags.is_empty() || todo.tag_list().any(|tag| tags.contains(&tag)))
+        .collect();
+
+    let (total, unestimated) = workload(&open);
+
+    let mut line = i18n::text(
+        config,
+        "workload",
+        &[("total", &total), ("count", &open.len())],
+    );
+
+    if unestimated > 0 {
+        line += &i18n::text(config, "workload-unestimated", &[("count", &unestimated)]);
+    }
+
+    println!("{line}");
+}
+
+/// A table of each tag's completed and total todos, least complete first.
+fn show_tag_progress(config: &Config) {
+    let todos: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+    let progress = tag_progress(&todos);
+
+    if progress.is_empty() {
+        println!("{}", i18n::text(config, "no-tags", &[]));
+        return;
+    }
+
+    let mut builder = Builder::default();
+    builder.push_record(["tag", "completed", "total", "%"]);
+
+    for (tag, completed, total) in progress {
+        builder.push_record([
+            tag.to_owned(),
+            completed.to_string(),
+            total.to_string(),
+            format!("{}%", percent(completed, total)),
+        ]);
+    }
+
+    let mut table = builder.build();
+    table.with(Style::modern());
+
+    println!("{table}");
+}
+
+/// `(tag, completed, total)` for every tag, sorted by completion ratio and
+/// then by name. A todo with several tags counts toward each of them.
+fn tag_progress(todos: &[Todo]) -> Vec<(&str, usize, usize)> {
+    let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+
+    for todo in todos {
+        let tags: BTreeSet<&str> = todo.tag_list().collect();
+
+        for tag in tags {
+            let (completed, total) = counts.entry(tag).or_default();
+            *completed += usize::from(todo.completed);
+            *total += 1;
+        }
+    }
+
+    let mut progress: Vec<(&str, usize, usize)> = counts
+        .into_iter()
+        .map(|(tag, (completed, total))| (tag, completed, total))
+        .collect();
+
+    // Cross-multiplied so equal ratios tie exactly and fall back to the name.
+    progress.sort_by(|(a_tag, a_done, a_total), (b_tag, b_done, b_total)| {
+        (a_done * b_total)
+            .cmp(&(b_done * a_total))
+            .then_with(|| a_tag.cmp(b_tag))
+    });
+
+    progress
+}
+
+/// Summed estimates of `todos`, and how many have none.
+fn workload(todos: &[&Todo]) -> (u64, usize) {
+    let total = todos
+        .iter()
+        .filter_map(|todo| todo.estimate)
+        .map(u64::from)
+        .sum();
+    let unestimated = todos.iter().filter(|todo| todo.estimate.is_none()).count();
+
+    (total, unestimated)
+}
+
+/// The raw stored value of one field of `todo`, named like its column in
+/// any letter case (`task`, `completed_at`, ...). Empty for unset fields.
+fn field_value(config: &Config, todo: &Todo, field: &str) -> String {
+    let column = field.to_ascii_uppercase();
+
+    if !COLUMNS.contains(&column.as_str()) {
+        AppError::InvalidField {
+            field: field.to_owned(),
+        }
+        .exit(config)
+    }
+
+    match serde_json::to_value(todo) {
+        Ok(serde_json::Value::Object(mut fields)) => match fields.remove(&column) {
+            Some(serde_json::Value::String(text)) => text,
+            Some(serde_json::Value::Null) | None => String::new(),
+            Some(other) => other.to_string(),
+        },
+        _ => String::new(),
+    }
+}
+
+/// Prints each distinct tag with the number of todos using it, most used
+/// first.
+fn list_tags(config: &Config) {
+    let mut counts: Vec<(String, usize)> = Vec::new();
+
+    for todo in get_reader(config).deserialize::<Todo>().flatten() {
+        for tag in todo.tag_list() {
+            match counts.iter_mut().find(|(name, _)| name == tag) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((tag.to_owned(), 1)),
+            }
+        }
+    }
+
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let mut builder = Builder::default();
+    builder.push_record(["TAG", "COUNT"]);
+
+    for (tag, count) in counts {
+        builder.push_record([tag, count.to_string()]);
+    }
+
+    let mut table = builder.build();
+    table.with(Style::modern());
+
+    println!("{table}");
+}
+
+/// The widest cell of each column of `table`, in terminal columns.
+fn column_widths(table: &Table) -> Vec<usize> {
+    let mut widths = vec![0; table.count_columns()];
+
+    for row in table.get_records().iter() {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(display_width(cell.as_ref()));
+        }
+    }
+
+    widths
+}
+
+/// `--debug-layout`: one line per column with the width its content
+/// needs and the width it was given, e.g. after `--max-width` wrapping.
+fn print_layout(labels: &[impl AsRef<str>], measured: &[usize], rendered: &[usize]) {
+    for ((label, measured), rendered) in labels.iter().zip(measured).zip(rendered) {
+        let label = label.as_ref();
+        eprintln!("layout: {label:?} measured {measured} rendered {rendered}");
+    }
+}
+
+/// Number of terminal columns `text` occupies, so emoji and CJK characters
+/// count as two. Matches how `tabled` sizes its columns.
+fn display_width(text: &str) -> usize {
+    text.lines().map(UnicodeWidthStr::width).max().unwrap_or(0)
+}
+
+/// One `list --porcelain` line: ID, `true`/`false` and the task, separated
+/// by tabs. Backslashes, tabs and line breaks in the task are escaped as
+/// `\\`, `\t`, `\n` and `\r` so every todo stays on one line with three
+/// fields. This format is documented as stable: don't change it.
+fn porcelain_line(todo: &Todo) -> String {
+    let mut task = String::with_capacity(todo.task.len());
+
+    for c in todo.task.chars() {
+        match c {
+            '\\' => task.push_str("\\\\"),
+            '\t' => task.push_str("\\t"),
+            '\n' => task.push_str("\\n"),
+            '\r' => task.push_str("\\r"),
+            c => task.push(c),
+        }
+    }
+
+    format!("{}\t{}\t{task}", todo.id, todo.completed)
+}
+
+/// Prints the todos `list` would show as porcelain lines, in database
+/// order, without headers, colors or a footer.
+fn print_porcelain(config: &Config) {
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+
+    for todo in listed_rows(config, &mut get_file_reader(config)).flatten() {
+        if let Err(e) = writeln!(out, "{}", porcelain_line(&todo)) {
+            eprintln!("Failed to write to stdout: {e}");
+            exit(1);
+        }
+    }
+}
+
+/// The cells `stream_todos` prints for `todo`, in column order.
+fn display_fields<'a>(config: &'a Config, todo: &'a Todo) -> Vec<Cow<'a, str>> {
+    let mut fields = todo.fields();
+    fields[COMPLETED_COLUMN] = Cow::Borrowed(config.completion_symbol(todo.completed));
+    fields
+}
+
+/// Renders the same table as `list_todos` without holding every record in
+/// memory: a first pass over the file measures the column widths and a
+/// second pass writes each row to `out` as it's read.
+fn stream_todos(config: &Config, out: &mut impl Write) {
+    let headers = config.headers.labels();
+    let mut widths: Vec<usize> = headers.iter().map(|h| display_width(h)).collect();
+
+    for todo in listed_rows(config, &mut get_file_reader(config)).flatten() {
+        for (width, field) in widths.iter_mut().zip(display_fields(config, &todo)) {
+            *width = (*width).max(display_width(&field));
+        }
+    }
+
+    // Streamed columns are never narrowed, so both widths are the same.
+    if config.debug_layout {
+        print_layout(&headers, &widths, &widths);
+    }
+
+    let border = |left: &str, mid: &str, right: &str| {
+        let segments: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+        format!("{left}{}{right}", segments.join(mid))
+    };
+    let row = |cells: Vec<Cow<'_, str>>, task_color: Option<&str>| {
+        let cells: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (cell, w))| {
+                let padding = " ".repeat(w - display_width(cell));
+                match task_color {
+                    Some(code) if i == TASK_COLUMN => format!(" {code}{cell}{RESET}{padding} "),
+                    _ => format!(" {cell}{padding} "),
+                }
+            })
+            .collect();
+        format!("│{}│", cells.join("│"))
+    };
+
+    let separator = border("├", "┼", "┤");
+
+    let mut write = |line: &str| {
+        if let Err(e) = writeln!(out, "{line}") {
+            eprintln!("Failed to write to stdout: {e}");
+            exit(1);
+        }
+    };
+
+    if let Some(header) = list_header(config) {
+        write(&header);
+    }
+
+    write(&border("┌", "┬", "┐"));
+    write(&row(headers.map(Cow::from).to_vec(), None));
+
+    let (mut completed, mut total) = (0, 0);
+
+    for result in listed_rows(config, &mut get_file_reader(config)) {
+        match result {
+            Ok(todo) => {
+                total += 1;
+                completed += usize::from(todo.completed);
+                write(&separator);
+                let color = ansi_code(&todo.color).filter(|_| config.color);
+                write(&row(display_fields(config, &todo), color));
+            }
+            Err(e) => eprintln!("Failed to parse csv row: {e:?}"),
+        }
+    }
+
+    write(&border("└", "┴", "┘"));
+
+    if config.show_footer && !config.quiet {
+        write(&progress_footer(config, completed, total));
+
+        if let Some(goal) = goal_line(config) {
+            write(&goal);
+        }
+    }
+}
+
+/// Fails when adding `adding` todos to `active` incomplete ones would go
+/// over `max_active`, unless `--force` was passed.
+fn check_active_limit(config: &Config, active: usize, adding: usize) -> Result<(), AppError> {
+    let limit = config.max_active;
+
+    if limit == 0 || adding == 0 || config.force || active + adding <= limit {
+        return Ok(());
+    }
+
+    Err(AppError::TooManyActive { active, limit })
+}
+
+/// `log`: records something already done, completed the moment it's
+/// created, and returns its ID.
+fn log_todo(config: &Config, todo: Todo, note: Option<&str>) -> String {
+    let todo = Todo {
+        completed: true,
+        completed_at: todo.created_at,
+        times_completed: 1,
+        notes: note.unwrap_or_default().to_owned(),
+        ..todo
+    };
+
+    create_todo(config, todo, false)
+}
+
+/// Adds one todo and returns its ID.
+fn create_todo(config: &Config, new_task: Todo, at_top: bool) -> String {
+    create_todos(config, vec![new_task], at_top).remove(0)
+}
+
+/// Adds `new_tasks` in a single write, numbering them contiguously after
+/// the existing todos, and returns the IDs they were given.
+fn create_todos(config: &Config, mut new_tasks: Vec<Todo>, at_top: bool) -> Vec<String> {
+    if config.max_active > 0 {
+        let active = get_reader(config)
+            .deserialize::<Todo>()
+            .flatten()
+            .filter(|todo| !todo.completed)
+            .count();
+
+        let adding = new_tasks.iter().filter(|todo| !todo.completed).count();
+
+        if let Err(e) = check_active_limit(config, active, adding) {
+            e.exit(config)
+        }
+    }
+
+    let mut reader = get_reader(config);
+    let next_id = TodoId::try_from(reader.records().count())
+        .ok()
+        .and_then(|count| count.checked_add(1))
+        .unwrap_or_else(|| AppError::IdOverflow.exit(config));
+
+    for (offset, todo) in (0..).zip(&mut new_tasks) {
+        let id = next_id
+            .checked_add(offset)
+            .unwrap_or_else(|| AppError::IdOverflow.exit(config));
+        todo.id = id.to_string();
+    }
+
+    // Rows are appended in `COLUMNS` order, so a header naming other
+    // columns, from before a field was added, or the same ones in another
+    // order would shift the new row's fields. Upgrade the whole file to
+    // the current schema instead.
+    let header_is_current = match reader.headers() {
+        Ok(headers) => headers.is_empty() || headers.iter().eq(COLUMNS),
+        Err(_) => false,
+    };
+
+    if at_top
+        || !header_is_current
+        || !storage(config).appends()
+        || storage::is_encrypted(&config.database)
+    {
+        let mut records: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+
+        let ids = insert_todos(&mut records, new_tasks, at_top);
+
+        write_to_database(config, records);
+        audit::record(config, "create", &ids);
+        return ids;
+    }
+
+    ensure_writable(config);
+
+    let has_headers = next_id == 1;
+    let mut writer = WriterBuilder::new()
+        .delimiter(config.delimiter)
+        .has_headers(has_headers)
+        .from_writer(Vec::new());
+
+    let mut ids = Vec::new();
+
+    for todo in new_tasks {
+        ids.push(todo.id.clone());
+
+        if let Err(e) = writer.serialize(todo) {
+            println!("Failed to write new todo to db: {e:?}");
+            break;
+        }
+    }
+
+    let rows = writer.into_inner().unwrap_or_else(|e| {
+        println!("Failed to write new todo to db: {e:?}");
+        exit(1);
+    });
+
+    let mut file = match OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&config.database)
+    {
+        Ok(w) => w,
+        Err(e) => {
+            println!("Failed to open db.csv: {e:?}");
+            exit(1);
+        }
+    };
+
+    // One write of whole rows: with O_APPEND each lands at the end in one
+    // piece, so rows from processes appending at once never interleave.
+    if let Err(e) = file.write_all(&rows) {
+        println!("Failed to write new todo to db: {e:?}");
+        exit(1);
+    }
+
+    audit::record(config, "create", &ids);
+    ids
+}
+
+/// Adds `new_tasks` to the end of `records`, or the start with `at_top`,
+/// and returns the IDs they were given.
+fn insert_todos(records: &mut Vec<Todo>, new_tasks: Vec<Todo>, at_top: bool) -> Vec<String> {
+    let created = new_tasks.len();
+    let start = if at_top { 0 } else { records.len() };
+    records.splice(start..start, new_tasks);
+
+    *records = reindex(std::mem::take(records));
+
+    records[start..start + created]
+        .iter()
+        .map(|todo| todo.id.clone())
+        .collect()
+}
+
+/// Orders todos so each subtask follows its parent, indenting its text by
+/// depth, or with `tree` drawing `├─`/`└─` connectors down to it. Subtasks
+/// whose parent isn't listed, or that are caught in a parent cycle from a
+/// hand-edited file, stay at the top level.
+fn nest_subtasks(todos: Vec<Todo>, tree: bool) -> Vec<Todo> {
+    if todos.iter().all(|todo| todo.parent_id.is_empty()) {
+        return todos;
+    }
+
+    let listed: HashSet<&str> = todos.iter().map(|todo| todo.id.as_str()).collect();
+    let mut children: HashMap<&str, Vec<usize>> = HashMap::new();
+    let mut roots = Vec::new();
+
+    for (idx, todo) in todos.iter().enumerate() {
+        if todo.parent_id != todo.id && listed.contains(todo.parent_id.as_str()) {
+            children.entry(&todo.parent_id).or_default().push(idx);
+        } else {
+            roots.push(idx);
+        }
+    }
+
+    let mut order = Vec::with_capacity(todos.len());
+    let mut visited = vec![false; todos.len()];
+
+    for start in roots.into_iter().chain(0..todos.len()) {
+        // Each entry carries its connector and the guides its own subtasks
+        // continue from: `│` below ancestors with siblings still to come.
+        let mut stack = vec![(start, 0, String::new(), String::new())];
+
+        while let Some((idx, depth, branch, guides)) = stack.pop() {
+            if std::mem::replace(&mut visited[idx], true) {
+                continue;
+            }
+
+            order.push((idx, depth, branch));
+
+            if let Some(subtasks) = children.get(todos[idx].id.as_str()) {
+                let last = subtasks.len() - 1;
+
+                stack.extend(subtasks.iter().enumerate().rev().map(|(i, &sub)| {
+                    let (branch, guide) = if i == last {
+                        ("└─ ", "   ")
+                    } else {
+                        ("├─ ", "│  ")
+                    };
+                    (
+                        sub,
+                        depth + 1,
+                        format!("{guides}{branch}"),
+                        format!("{guides}{guide}"),
+                    )
+                }));
+            }
+        }
+    }
+
+    let mut slots: Vec<Option<Todo>> = todos.into_iter().map(Some).collect();
+
+    order
+        .into_iter()
+        .filter_map(|(idx, depth, branch)| {
+            let mut todo = slots[idx].take()?;
+
+            if tree {
+                todo.task = format!("{branch}{}", todo.task);
+            } else if depth > 0 {
+                todo.task = format!("{}└ {}", "  ".repeat(depth - 1), todo.task);
+            }
+
+            Some(todo)
+        })
+        .collect()
+}
+
+/// IDs of every subtask below `id`, however deeply nested.
+fn subtask_ids(records: &[Todo], id: &str) -> HashSet<String> {
+    let mut found = HashSet::new();
+    let mut pending = vec![id.to_owned()];
+
+    while let Some(parent) = pending.pop() {
+        for todo in records.iter().filter(|todo| todo.parent_id == parent) {
+            if todo.id != id && found.insert(todo.id.clone()) {
+                pending.push(todo.id.clone());
+            }
+        }
+    }
+
+    found
+}
+
+/// A todo with subtasks counts as completed exactly when all of them are.
+/// Repeats until nothing changes so the state carries up nested levels,
+/// bounded in case a hand-edited file holds a cycle.
+fn derive_parent_completion(records: &mut [Todo]) {
+    for _ in 0..records.len() {
+        let mut all_done: HashMap<&str, bool> = HashMap::new();
+
+        for todo in records.iter().filter(|todo| !todo.parent_id.is_empty()) {
+            *all_done.entry(&todo.parent_id).or_insert(true) &= todo.completed;
+        }
+
+        let changed: Vec<(usize, bool)> = records
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, todo)| {
+                let done = *all_done.get(todo.id.as_str())?;
+                (done != todo.completed).then_some((idx, done))
+            })
+            .collect();
+
+        if changed.is_empty() {
+            return;
+        }
+
+        for (idx, done) in changed {
+            if done {
+                records[idx].complete();
+            } else {
+                records[idx].reopen();
+            }
+        }
+    }
+}
+
+/// Adds `subtask` under `parent_id`. This rewrites the file so a completed
+/// parent is reopened by its new open subtask.
+fn add_subtask(config: &Config, parent_id: &str, subtask: Todo) {
+    let mut records: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+    let active = records.iter().filter(|todo| !todo.completed).count();
+
+    if let Err(e) = check_active_limit(config, active, 1) {
+        e.exit(config)
+    }
+
+    let id = insert_subtask(&mut records, parent_id, subtask).unwrap_or_else(|e| e.exit(config));
+
+    write_to_database(config, records);
+    audit::record(config, "add-sub", &[id]);
+}
+
+/// Appends `subtask` under `parent_id` and returns its new ID.
+fn insert_subtask(
+    records: &mut Vec<Todo>,
+    parent_id: &str,
+    mut subtask: Todo,
+) -> Result<String, AppError> {
+    if !records.iter().any(|todo| todo.id == parent_id) {
+        return Err(AppError::NotFound {
+            id: parent_id.to_owned(),
+        });
+    }
+
+    let next_id = TodoId::try_from(records.len())
+        .ok()
+        .and_then(|count| count.checked_add(1))
+        .ok_or(AppError::IdOverflow)?;
+    subtask.id = next_id.to_string();
+    subtask.parent_id = parent_id.to_owned();
+
+    records.push(subtask);
+    Ok(next_id.to_string())
+}
+
+/// Shows `records` as `list` would after a change, without saving them.
+fn print_preview(config: &Config, mut records: Vec<Todo>) {
+    derive_parent_completion(&mut records);
+
+    records.retain(|todo| config.shows(todo.completed));
+    sort_listed(config, &mut records);
+
+    println!("{}", render_todos(config, records));
+    println!("{}", i18n::text(config, "preview-only", &[]));
+}
+
+/// With `--read-only`, stops any command about to change the database,
+/// the archive or the config before it writes anything.
+fn ensure_writable(config: &Config) {
+    if config.read_only {
+        AppError::ReadOnly.exit(config)
+    }
+}
+
+/// The moment `age` (e.g. `7d`) before now, or `None` without one.
+fn age_cutoff(config: &Config, age: Option<&str>) -> Option<DateTime<Utc>> {
+    let age = age?;
+    let Some(age) = parse_duration(age) else {
+        AppError::InvalidAge {
+            age: age.to_owned(),
+        }
+        .exit(config)
+    };
+
+    Some(Utc::now() - age)
+}
+
+/// Replaces the database with `records`. Rows go to a temporary file next
+/// to the database which is then renamed over it, so readers only ever see
+/// the old or the new contents, never a partly written file.
+fn write_to_database(config: &Config, mut records: Vec<Todo>) {
+    ensure_writable(config);
+    derive_parent_completion(&mut records);
+
+    let file_name = config.database.file_name().unwrap_or_default();
+    let temp_path = config.database.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        process::id()
+    ));
+
+    let contents = storage(config)
+        .serialize(config, &records)
+        .unwrap_or_else(|e| {
+            println!("Failed to write updated todo to db: {e}");
+            exit(1);
+        });
+
+    let mut file = match File::create(&temp_path) {
+        Ok(w) => w,
+        Err(e) => {
+            println!("Failed to create {}: {e:?}", temp_path.display());
+            exit(1);
+        }
+    };
+
+    let synced = file.write_all(&contents).and_then(|_| file.sync_all());
+
+    if let Err(e) = synced.and_then(|_| fs::rename(&temp_path, &config.database)) {
+        println!("Failed to save db.csv: {e:?}");
+        let _ = fs::remove_file(&temp_path);
+        exit(1);
+    }
+}
+
+/// Completes the todo along with any subtasks still open under it.
+fn complete_todo(config: &Config, id: String, note: Option<&str>) {
+    let mut records = records_to_rewrite(config).unwrap_or_else(|e| e.exit(config));
+
+    let were_completed: HashSet<String> = records
+        .iter()
+        .filter(|record| record.completed)
+        .map(|record| record.id.clone())
+        .collect();
+
+    let Some(completed_ids) = mark_completed(&mut records, &id, note) else {
+        println!(
+            "{}",
+            i18n::text(config, "missing-or-completed", &[("id", &id)])
+        );
+        list_todos(config);
+        return;
+    };
 
-static DATABASE_PATH: &str = "data/db.csv";
-static DATABASE_DIR: &str = "data";
+    let time_open = records
+        .iter()
+        .find(|record| record.id == id)
+        .and_then(|record| record.age(Utc::now()));
 
-#[derive(Debug, Serialize, Deserialize, Tabled, Clone)]
-struct Todo {
-    #[serde(rename = "ID")]
-    id: String,
-    #[serde(rename = "TASK")]
-    task: String,
-    #[serde(rename = "COMPLETED")]
-    completed: bool,
-}
+    let archived = settle_completion(config, &mut records, &were_completed);
+    let remaining = records.iter().filter(|record| !record.completed).count();
 
-impl Todo {
-    fn new(id: usize, task: &str) -> Todo {
-        Todo {
-            id: id.to_string(),
-            task: task.to_owned(),
-            completed: false,
-        }
+    if config.preview {
+        print_preview(config, records);
+        return;
     }
-}
 
-fn main() -> ExitCode {
-    create_db_if_not_exists();
+    println!("{}", i18n::text(config, "updating", &[("id", &id)]));
 
-    let args = App::new("todosh.rs")
-        .version("1.0.0")
-        .about("Terminal based todo list app")
-        .arg(
-            Arg::with_name("command")
-                .help("The command to run")
-                .takes_value(true)
-                .required(true),
-        )
-        .arg(
-            Arg::with_name("input")
-                .help("The input for the command")
-                .takes_value(true)
-                .required(false),
-        )
-        .get_matches();
+    if !archived.is_empty() {
+        append_to_archive(config, &archived);
+        records = reindex(records);
+    }
 
-    if args.value_of("command").is_none() {
-        println!("<command> is required");
-        exit(1)
+    write_to_database(config, records);
+    audit::record(config, "complete", &completed_ids);
+    // Archived todos are gone and the rest renumbered, so there's nothing
+    // left to undo.
+    remember::record_completion(
+        config,
+        if archived.is_empty() {
+            &completed_ids
+        } else {
+            &[]
+        },
+    );
+    list_todos(config);
+
+    if let Some(time_open) = time_open {
+        let duration = format_duration(time_open);
+        println!(
+            "{}",
+            i18n::text(config, "completed-after", &[("duration", &duration)])
+        );
     }
 
-    let pattern = args.value_of("command").unwrap();
+    if !config.quiet {
+        let key = match remaining {
+            0 => "none-left",
+            1 => "one-left",
+            _ => "some-left",
+        };
+        println!("{}", i18n::text(config, key, &[("count", &remaining)]));
+    }
 
-    if !matches!(
-        pattern,
-        "create" | "update" | "delete" | "list" | "complete"
-    ) {
-        println!("Invalid command");
-        exit(1)
+    if !archived.is_empty() {
+        let ids: Vec<String> = archived.iter().map(|todo| todo.id.clone()).collect();
+        audit::record(config, "archive", &ids);
+        let path = config.archive_path();
+
+        let message = match archived.as_slice() {
+            [todo] => i18n::text(
+                config,
+                "archived-task-to",
+                &[("task", &todo.task), ("path", &path.display())],
+            ),
+            _ => i18n::text(
+                config,
+                "archived-to",
+                &[
+                    ("count", &archived.len()),
+                    ("ids", &ids.join(", ")),
+                    ("path", &path.display()),
+                ],
+            ),
+        };
+        println!("{message}");
     }
+}
 
-    match pattern {
-        "list" => list_todos(),
-        "create" => {
-            let mut input = String::new();
+/// `finish`: completes `id` and moves it, with any subtasks completed
+/// along with it, straight to the archive, whatever
+/// `auto_archive_on_complete` says.
+fn finish_todo(config: &Config, id: String, note: Option<&str>) {
+    let config = Config {
+        auto_archive_on_complete: true,
+        ..config.clone()
+    };
 
-            if let Some(input_str) = args.value_of("input") {
-                input = input_str.to_string();
-            } else {
-                println!("Enter new task (press enter to submit):");
+    complete_todo(&config, id, note);
+}
 
-                if let Err(error) = io::stdin().read_line(&mut input) {
-                    println!("error: {error}");
-                    exit(1);
-                }
-            }
+/// `complete --undo`: reopens the todos the last `complete` marked done,
+/// subtasks included. Parents follow their subtasks when saved.
+fn undo_completion(config: &Config) {
+    ensure_writable(config);
+    let ids = remember::take_completion(config);
+    let mut records: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+    let mut reopened = Vec::new();
 
-            create_todo(input);
-            list_todos();
+    for record in &mut records {
+        if record.completed && ids.contains(&record.id) {
+            record.reopen();
+            reopened.push(record.id.clone());
         }
-        "complete" => {
-            let value = args.value_of("input");
+    }
 
-            if let Some(id) = value {
-                let id: usize = id.parse().expect("error: Invalid Todo id supplied");
-                let mut reader = get_reader();
+    if reopened.is_empty() {
+        AppError::NothingToUndo.exit(config)
+    }
 
-                if id > reader.records().count() {
-                    println!("error: No Todo with ID {id}");
-                    exit(1)
-                };
+    write_to_database(config, records);
+    audit::record(config, "uncomplete", &reopened);
+    list_todos(config);
+    println!(
+        "{}",
+        i18n::text(config, "reopened-many", &[("ids", &reopened.join(", "))])
+    );
+}
 
-                complete_todo(id.to_string());
-            } else {
-                println!("error: Id is expected");
-                exit(1);
-            }
-        }
-        "update" => {
-            let value = args.value_of("input");
+/// Flips a completed todo back to pending: `id`, or the most recently
+/// completed todo when `None`.
+fn reopen_todo(config: &Config, id: Option<String>) {
+    let mut records: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
 
-            if value.is_none() {
-                println!("error: Id is expected");
-                exit(1)
-            }
+    let Some(id) = id.or_else(|| last_completed(&records)) else {
+        AppError::NothingCompleted.exit(config)
+    };
 
-            let id: usize = value.unwrap().parse().expect("Invalid ID passed");
-            let mut reader = get_reader();
+    if let Err(e) = mark_pending(&mut records, &id) {
+        e.exit(config)
+    }
 
-            if id > reader.records().count() {
-                println!("error: No Todo with ID {id}");
-                exit(1)
-            };
+    if config.preview {
+        print_preview(config, records);
+        return;
+    }
 
-            update_todo(id.to_string());
-        }
-        "delete" => {
-            let value = args.value_of("input");
+    let task = records
+        .iter()
+        .find(|record| record.id == id)
+        .map(|record| record.task.clone())
+        .unwrap_or_default();
 
-            if value.is_none() {
-                println!("error: Id is expected");
-                exit(1);
-            }
+    write_to_database(config, records);
+    audit::record(config, "uncomplete", std::slice::from_ref(&id));
+    list_todos(config);
+    println!(
+        "{}",
+        i18n::text(config, "reopened", &[("id", &id), ("task", &task)])
+    );
+}
 
-            let id: u16 = value.unwrap().parse().expect("Invalid Todo id supplied");
+/// Moves a todo's priority one level up (`raise`) or down, reporting the
+/// new one. Nothing is written when it's already at that end.
+fn shift_priority(config: &Config, id: &str, up: bool) {
+    let mut records: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
 
-            delete_todo(id.to_string())
-        }
-        _ => println!("{pattern} ran successfully"),
+    let Some(todo) = records.iter_mut().find(|record| record.id == id) else {
+        AppError::NotFound { id: id.to_owned() }.exit(config)
+    };
+
+    let old = todo.priority;
+    todo.priority = if up { old.raised() } else { old.lowered() };
+    let new = todo.priority;
+
+    if new == old {
+        println!(
+            "{}",
+            i18n::text(
+                config,
+                "priority-unchanged",
+                &[("id", &id), ("priority", &old)]
+            )
+        );
+        return;
     }
 
-    ExitCode::SUCCESS
+    if config.preview {
+        print_preview(config, records);
+        return;
+    }
+
+    write_to_database(config, records);
+    audit::record(config, if up { "raise" } else { "lower" }, &[id.to_owned()]);
+    println!(
+        "{}",
+        i18n::text(
+            config,
+            "priority-changed",
+            &[("id", &id), ("old", &old), ("new", &new)]
+        )
+    );
 }
 
-fn create_db_if_not_exists() {
-    if !Path::new(DATABASE_DIR).exists() {
-        if let Err(e) = fs::create_dir_all(DATABASE_DIR) {
-            eprintln!("Failed to create database directory: {e}");
-            exit(1);
-        }
+/// The completed todo with the latest `completed_at`. Todos completed
+/// before timestamps were recorded count as older, highest ID last.
+fn last_completed(records: &[Todo]) -> Option<String> {
+    records
+        .iter()
+        .filter(|todo| todo.completed)
+        .max_by_key(|todo| (todo.completed_at, todo.numeric_id()))
+        .map(|todo| todo.id.clone())
+}
+
+/// Marks `id` as pending again. A parent's completion follows its subtasks,
+/// so it can only be reopened by reopening one of them.
+fn mark_pending(records: &mut [Todo], id: &str) -> Result<(), AppError> {
+    if !subtask_ids(records, id).is_empty() {
+        return Err(AppError::HasSubtasks { id: id.to_owned() });
     }
 
-    let db_exists = Path::new(DATABASE_PATH).is_file();
+    let Some(record) = records.iter_mut().find(|record| record.id == id) else {
+        return Err(AppError::NotFound { id: id.to_owned() });
+    };
 
-    if db_exists {
-        return;
+    if !record.completed {
+        return Err(AppError::NotCompleted { id: id.to_owned() });
     }
 
-    match File::create(DATABASE_PATH) {
-        Ok(_) => {
-            println!("Database created...")
-        }
-        Err(e) => {
-            eprintln!("Failed to create database: {e:?}");
-            exit(1);
-        }
-    }
+    record.reopen();
+    Ok(())
 }
 
-fn get_reader() -> Reader<File> {
-    match ReaderBuilder::new()
-        .trim(csv::Trim::All)
-        .from_path(DATABASE_PATH)
-    {
-        Ok(rdr) => rdr,
-        Err(e) => {
-            eprintln!("Failed to create csv reader: {e:?}");
-            exit(1);
-        }
+/// Follows up `mark_completed`: parents completed by their last subtask
+/// count as done, and with `auto_archive_on_complete` everything completed
+/// since `were_completed` is taken out of `records` and returned, to be
+/// appended to the archive.
+fn settle_completion(
+    config: &Config,
+    records: &mut Vec<Todo>,
+    were_completed: &HashSet<String>,
+) -> Vec<Todo> {
+    derive_parent_completion(records);
+
+    if !config.auto_archive_on_complete {
+        return Vec::new();
     }
+
+    let (archived, kept) = std::mem::take(records)
+        .into_iter()
+        .partition(|record: &Todo| record.completed && !were_completed.contains(&record.id));
+    *records = kept;
+
+    archived
 }
 
-fn list_todos() {
-    let mut reader = get_reader();
-    let mut table_data: Vec<Todo> = Vec::new();
+/// Completes `id` and its open subtasks, returning every ID it completed
+/// with `id` first. `None` if `id` doesn't exist or is already completed.
+fn mark_completed(records: &mut [Todo], id: &str, note: Option<&str>) -> Option<Vec<String>> {
+    let target = records
+        .iter()
+        .position(|record| record.id == id && !record.completed)?;
+    let subtasks = subtask_ids(records, id);
+    let mut completed_ids = vec![id.to_owned()];
 
-    for result in reader.deserialize() {
-        let record: Todo = match result {
-            Ok(row) => row,
-            Err(e) => {
-                println!("Failed to parse csv row: {e:?}");
-                Todo {
-                    id: String::new(),
-                    task: String::new(),
-                    completed: false,
+    for (idx, record) in records.iter_mut().enumerate() {
+        if idx == target {
+            record.complete();
+
+            if let Some(note) = note {
+                if !record.notes.is_empty() {
+                    record.notes.push('\n');
                 }
+                record.notes.push_str(note);
             }
-        };
-        table_data.push(record);
+        } else if subtasks.contains(&record.id) && !record.completed {
+            completed_ids.push(record.id.clone());
+            record.complete();
+        }
     }
 
-    let mut table = Table::new(table_data);
-    table.with(Style::modern());
+    Some(completed_ids)
+}
 
-    println!("{table}");
+fn delete_todo(config: &Config, id: String) {
+    let mut records: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+
+    if !records.iter().any(|record| record.id == id) {
+        not_found_in(&records, &id).exit(config)
+    }
+
+    let deleted = remove_todo(&mut records, &id).unwrap_or_else(|e| e.exit(config));
+
+    write_to_database(config, records);
+    audit::record(config, "delete", std::slice::from_ref(&id));
+    list_todos(config);
+    println!(
+        "{}",
+        i18n::text(config, "deleted", &[("task", &deleted.task), ("id", &id)])
+    );
 }
 
-fn create_todo(input: String) {
-    let mut reader = get_reader();
-    let next_id = reader.records().count() + 1;
-    let new_task = Todo::new(next_id, input.trim_end());
+/// Takes `id` out of `records` and reindexes the rest. `records` is left
+/// untouched if there is no such todo.
+fn remove_todo(records: &mut Vec<Todo>, id: &str) -> Result<Todo, AppError> {
+    let Some(idx) = records.iter().position(|record| record.id == id) else {
+        return Err(AppError::NotFound { id: id.to_owned() });
+    };
 
-    let file = match OpenOptions::new()
-        .append(true)
-        .create(true)
-        .open(DATABASE_PATH)
-    {
-        Ok(w) => w,
-        Err(e) => {
-            println!("Failed to open db.csv: {e:?}");
-            exit(1);
+    let deleted = records.remove(idx);
+    *records = reindex(std::mem::take(records));
+    Ok(deleted)
+}
+
+/// Moves a todo to the end of another named list, creating that list if
+/// needed. The todo gets a new ID there and this list is reindexed.
+fn move_todo(config: &Config, id: &str, list: &str) {
+    let Some(target_path) = config.list_path(list) else {
+        AppError::InvalidList {
+            list: list.to_owned(),
         }
+        .exit(config)
     };
 
-    let has_headers = next_id == 1;
-    let mut writer = WriterBuilder::new()
-        .has_headers(has_headers)
-        .from_writer(file);
+    if fs::canonicalize(&target_path).ok() == fs::canonicalize(&config.database).ok() {
+        AppError::SameList {
+            list: list.to_owned(),
+        }
+        .exit(config)
+    }
 
-    match writer.serialize(new_task) {
-        Ok(_) => {
-            writer.flush().unwrap();
+    let (mut moved, remaining): (Vec<Todo>, Vec<Todo>) = get_reader(config)
+        .deserialize::<Todo>()
+        .flatten()
+        .partition(|todo| todo.id == id);
+
+    let Some(mut todo) = moved.pop() else {
+        AppError::NotFound { id: id.to_owned() }.exit(config)
+    };
+    // Its parent stays behind; subtasks left here become top-level todos.
+    todo.parent_id.clear();
+
+    let target = Config {
+        database: target_path,
+        ..config.clone()
+    };
+    create_db_if_not_exists(&target);
+
+    // The target is written first so a failure in between leaves the todo
+    // in both lists rather than in neither.
+    let new_ids = create_todos(&target, vec![todo], false);
+    write_to_database(config, reindex(remaining));
+    audit::record(config, "move-to", &[id.to_owned()]);
+
+    let source = config
+        .database
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy();
+    println!(
+        "{}",
+        i18n::text(
+            config,
+            "moved",
+            &[
+                ("id", &id),
+                ("source", &source),
+                ("list", &list),
+                ("ids", &new_ids.join(", ")),
+            ],
+        )
+    );
+}
+
+/// `reorder --from FILE`: renumbers the todos in the order their IDs
+/// appear in FILE, taken from the first tab-separated field of each line
+/// so rearranged `list --porcelain` output works as is. Nothing is changed
+/// unless FILE lists every todo exactly once.
+fn reorder_todos(config: &Config, path: &str) {
+    let order: Vec<String> = read_task_file(config, path)
+        .iter()
+        .map(|line| {
+            line.split('\t')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_owned()
+        })
+        .collect();
+    let records: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+    let mut by_id: HashMap<String, Todo> = records
+        .into_iter()
+        .map(|todo| (todo.id.clone(), todo))
+        .collect();
+
+    let mut reordered = Vec::new();
+    let mut unknown = Vec::new();
+
+    for id in order {
+        match by_id.remove(&id) {
+            Some(todo) => reordered.push(todo),
+            None => unknown.push(id),
         }
-        Err(e) => {
-            println!("Failed to write new todo to db: {e:?}");
+    }
+
+    if !by_id.is_empty() || !unknown.is_empty() {
+        let mut missing: Vec<Todo> = by_id.into_values().collect();
+        missing.sort_by_key(Todo::numeric_id);
+
+        AppError::OrderMismatch {
+            missing: missing.into_iter().map(|todo| todo.id).collect(),
+            unknown,
         }
+        .exit(config)
+    }
+
+    // The old IDs, in their new order.
+    let order: Vec<String> = reordered.iter().map(|todo| todo.id.clone()).collect();
+    let records = reindex(reordered);
+
+    if config.preview {
+        print_preview(config, records);
+        return;
+    }
+
+    write_to_database(config, records);
+    audit::record(config, "reorder", &order);
+    list_todos(config);
+    println!(
+        "{}",
+        i18n::text(config, "reordered", &[("count", &order.len())])
+    );
+}
+
+/// Copies the database into the `to` backend's format, beside it with that
+/// backend's extension (`data/db.csv` becomes `data/db.json`). The original
+/// is left untouched until the config points at the new file.
+fn migrate_database(config: &Config, to: Backend) {
+    let target = Config {
+        database: config.database.with_extension(to.name()),
+        backend: to,
+        ..config.clone()
     };
+
+    if to == config.backend {
+        let message = i18n::text(
+            config,
+            "already-stored-as",
+            &[
+                ("path", &config.database.display()),
+                ("backend", &to.name()),
+            ],
+        );
+        println!("error: {message}");
+        exit(1);
+    }
+
+    if target.database.exists() {
+        let message = i18n::text(
+            config,
+            "already-exists",
+            &[("path", &target.database.display())],
+        );
+        println!("error: {message}");
+        exit(1);
+    }
+
+    let records: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+    let count = records.len();
+
+    write_to_database(&target, records);
+
+    println!(
+        "{}",
+        i18n::text(
+            config,
+            "migrated",
+            &[
+                ("count", &count),
+                ("path", &target.database.display()),
+                ("backend", &to.name()),
+            ],
+        )
+    );
 }
 
-fn write_to_database(records: Vec<Todo>) {
-    let mut reader = get_reader();
+/// Rewrites the database with every value in its canonical form, such as
+/// `true`/`false` for each spelling `parse_bool` accepts and task text
+/// without padding, and returns how many cells changed. A file with rows
+/// that don't parse at all is left alone, since rewriting would drop them.
+fn normalize_database(config: &Config) -> usize {
+    if let Some(e) = first_malformed(config, get_reader(config)) {
+        e.exit(config)
+    }
 
-    match reader.get_mut().flush() {
-        Ok(_) => {
-            let file = match OpenOptions::new()
-                .write(true)
-                .truncate(true)
-                .open(DATABASE_PATH)
-            {
-                Ok(w) => w,
-                Err(e) => {
-                    println!("Failed to open db.csv: {e:?}");
-                    exit(1);
-                }
-            };
+    let mut reader = get_reader(config);
+    let headers = reader.headers().cloned().unwrap_or_default();
+    let mut todos = Vec::new();
+    let mut changed = 0;
 
-            let mut writer = WriterBuilder::new().has_headers(true).from_writer(file);
+    for record in reader.records().flatten() {
+        let Ok(todo) = record.deserialize::<Todo>(Some(&headers)) else {
+            continue;
+        };
 
-            for todo in records {
-                if let Err(e) = writer.serialize(todo) {
-                    println!("Failed to write updated todo to db: {e:?}");
-                    exit(1);
-                }
-            }
+        // Columns the file doesn't have yet are added, not normalized.
+        changed += headers
+            .iter()
+            .zip(&record)
+            .filter(|(header, cell)| {
+                COLUMNS.contains(header) && *cell != field_value(config, &todo, header)
+            })
+            .count();
+        todos.push(todo);
+    }
 
-            if let Err(e) = writer.flush() {
-                println!("Failed to flush writer: {e:?}");
-                exit(1);
-            }
-        }
-        Err(e) => {
-            println!("Failed to flush reader: {e:?}");
-            exit(1);
-        }
+    if changed > 0 {
+        write_to_database(config, todos);
     }
+
+    changed
 }
 
-fn complete_todo(id: String) {
-    let mut reader = get_reader();
-    let mut updated = false;
+/// Rewrites a database holding invalid UTF-8 as clean UTF-8, replacing
+/// each bad byte sequence with U+FFFD.
+fn repair_database(config: &Config) {
+    let contents = match fs::read(&config.database) {
+        Ok(contents) => storage::decrypt(config, contents).unwrap_or_else(|e| e.exit(config)),
+        Err(e) => AppError::ReadFailed {
+            path: config.database.display().to_string(),
+            reason: e.to_string(),
+        }
+        .exit(config),
+    };
+
+    let path = config.database.display();
+    let Err(e) = String::from_utf8(contents) else {
+        println!(
+            "{}",
+            i18n::text(config, "already-valid-utf8", &[("path", &path)])
+        );
+        return;
+    };
 
-    let updated_records = reader
+    let text = String::from_utf8_lossy(e.as_bytes()).into_owned();
+    let replaced = text.matches(char::REPLACEMENT_CHARACTER).count();
+    let records: Vec<Todo> = csv_reader(config, Cursor::new(as_csv(config, text.into_bytes())))
         .deserialize()
-        .map(|row| {
-            let mut record: Todo = row.unwrap();
+        .flatten()
+        .collect();
 
-            if id == record.id && !record.completed {
-                println!("Updating todo with id {id}...");
-                updated = true;
-                record.completed = true;
-            }
+    write_to_database(config, records);
+    println!(
+        "{}",
+        i18n::text(config, "repaired", &[("path", &path), ("count", &replaced)],)
+    );
+}
+
+/// Renumbers todos so IDs run contiguously from 1 in their current order.
+fn reindex(records: Vec<Todo>) -> Vec<Todo> {
+    let new_ids: HashMap<String, String> = records
+        .iter()
+        .enumerate()
+        .map(|(idx, record)| (record.id.clone(), (idx + 1).to_string()))
+        .collect();
 
+    records
+        .into_iter()
+        .enumerate()
+        .map(|(idx, mut record)| {
+            record.id = (idx + 1).to_string();
+            // Subtasks follow their parent to its new ID; those whose parent
+            // is gone become top-level todos.
+            record.parent_id = new_ids.get(&record.parent_id).cloned().unwrap_or_default();
             record
         })
-        .collect::<Vec<Todo>>();
+        .collect()
+}
+
+/// Moves completed todos into the archive file, optionally only those
+/// completed before `cutoff`. Completions without a recorded time are only
+/// archived when no cutoff is given.
+fn archive_todos(config: &Config, cutoff: Option<DateTime<Utc>>) {
+    let mut reader = get_reader(config);
+
+    let (archived, remaining): (Vec<Todo>, Vec<Todo>) =
+        reader.deserialize::<Todo>().flatten().partition(|record| {
+            record.completed
+                && match cutoff {
+                    Some(cutoff) => record.completed_at.is_some_and(|at| at < cutoff),
+                    None => true,
+                }
+        });
 
-    if !updated {
-        println!("Todo with ID '{id}' not found or already completed.");
-        list_todos();
+    if archived.is_empty() {
+        println!("{}", i18n::text(config, "nothing-to-archive", &[]));
         return;
-    };
+    }
 
-    write_to_database(updated_records);
-    list_todos();
+    append_to_archive(config, &archived);
+    write_to_database(config, reindex(remaining));
+
+    let ids: Vec<String> = archived.iter().map(|todo| todo.id.clone()).collect();
+    audit::record(config, "archive", &ids);
+    println!(
+        "{}",
+        i18n::text(
+            config,
+            "archived",
+            &[("count", &archived.len()), ("ids", &ids.join(", "))],
+        )
+    );
 }
 
-fn delete_todo(id: String) {
-    let mut reader = get_reader();
-    let mut updated = false;
-    let mut deleted = String::new();
+/// Archives, or with `delete` removes, incomplete todos created before
+/// `cutoff`. The stale todos are listed first and nothing changes unless
+/// the user confirms or passed `--yes`.
+fn expire_todos(config: &Config, cutoff: DateTime<Utc>, delete: bool, yes: bool) {
+    let records: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
 
-    let mut updated_records: Vec<Todo> = reader
-        .deserialize::<Todo>()
-        .filter_map(|row| {
-            if let Ok(record) = row {
-                if record.id == id {
-                    updated = true;
-                    deleted = record.task
-                } else {
-                    return Some(record);
-                }
-            }
+    let (stale, remaining): (Vec<Todo>, Vec<Todo>) = records
+        .into_iter()
+        .partition(|record| !record.completed && record.created_at.is_some_and(|at| at < cutoff));
 
-            None
-        })
-        .collect();
+    if stale.is_empty() {
+        println!(
+            "{}",
+            i18n::text(
+                config,
+                "nothing-to-expire",
+                &[("cutoff", &config.timezone.render(cutoff))],
+            )
+        );
+        return;
+    }
 
-    if updated {
-        // update ids
-        updated_records = updated_records
-            .into_iter()
-            .enumerate()
-            .map(|(idx, mut record)| {
-                let id = record.id.parse::<usize>().unwrap();
-                if id != idx + 1 {
-                    record.id = (idx + 1).to_string();
-                }
+    println!(
+        "{}",
+        render_table(config, &stale.iter().collect::<Vec<&Todo>>())
+    );
 
-                return record;
-            })
-            .collect()
+    if config.preview {
+        println!("{}", i18n::text(config, "preview-only", &[]));
+        return;
+    }
+
+    let question = if delete {
+        "confirm-delete"
     } else {
-        println!("Todo with ID '{id}' not found");
-        exit(1)
+        "confirm-archive"
+    };
+
+    let confirmed = yes
+        || prompt_line(
+            config,
+            &i18n::text(config, question, &[("count", &stale.len())]),
+        )
+        .and_then(|answer| parse_bool(&answer))
+        .unwrap_or(false);
+
+    if !confirmed {
+        println!("{}", i18n::text(config, "nothing-changed", &[]));
+        return;
     }
 
-    write_to_database(updated_records);
-    list_todos();
-    println!("Deleted task \"{deleted}\" with ID \"{id}\"");
+    if !delete {
+        append_to_archive(config, &stale);
+    }
+
+    write_to_database(config, reindex(remaining));
+
+    let ids: Vec<String> = stale.iter().map(|todo| todo.id.clone()).collect();
+    audit::record(config, "expire", &ids);
+    println!(
+        "{}",
+        i18n::text(
+            config,
+            if delete { "deleted-many" } else { "archived" },
+            &[("count", &stale.len()), ("ids", &ids.join(", "))],
+        )
+    );
 }
 
-fn update_todo(id: String) {
-    let mut reader = get_reader();
-    let mut updated = false;
+/// Appends `todos` to the archive file, writing its header first if the
+/// file is new.
+fn append_to_archive(config: &Config, todos: &[Todo]) {
+    ensure_writable(config);
 
-    let updated_records = reader
-        .deserialize()
-        .map(|row| {
-            let mut record: Todo = row.unwrap();
+    let archive_path = config.archive_path();
 
-            if id == record.id {
-                let mut input = String::new();
-                println!("Update todo ({}):", record.task);
+    // Encrypted, the archive can't be appended to; it's read and saved
+    // whole, like the database.
+    if config.encrypt || storage::is_encrypted(&archive_path) {
+        let archive = Config {
+            database: archive_path,
+            backend: Backend::Csv,
+            ..config.clone()
+        };
+        let mut records: Vec<Todo> = if archive.database.is_file() {
+            get_reader(&archive).deserialize().flatten().collect()
+        } else {
+            Vec::new()
+        };
 
-                if let Err(error) = io::stdin().read_line(&mut input) {
-                    println!("error: {error}");
-                    exit(1);
-                }
+        records.extend(todos.iter().cloned());
+        write_to_database(&archive, records);
+        return;
+    }
+    let archive_exists = fs::metadata(&archive_path).is_ok_and(|m| m.len() > 0);
+    let file = match OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&archive_path)
+    {
+        Ok(w) => w,
+        Err(e) => {
+            println!("Failed to open {}: {e:?}", archive_path.display());
+            exit(1);
+        }
+    };
 
-                if !input.trim().is_empty() && input.ne(&record.task) {
-                    record.task = input;
-                    updated = true;
-                }
-            }
+    let mut writer = WriterBuilder::new()
+        .delimiter(config.delimiter)
+        .has_headers(!archive_exists)
+        .from_writer(file);
 
-            record
-        })
-        .collect::<Vec<Todo>>();
+    for todo in todos {
+        if let Err(e) = writer.serialize(todo) {
+            println!("Failed to write todo to archive: {e:?}");
+            exit(1);
+        }
+    }
+
+    if let Err(e) = writer.flush() {
+        println!("Failed to flush writer: {e:?}");
+        exit(1);
+    }
+}
+
+fn update_todo(config: &Config, id: String, text: Option<String>) {
+    let mut records = records_to_rewrite(config).unwrap_or_else(|e| e.exit(config));
 
-    if updated {
-        write_to_database(updated_records);
+    if let Some(record) = records.iter().find(|record| record.id == id) {
+        let input = text.unwrap_or_else(|| {
+            let prompt = i18n::text(config, "prompt-update", &[("task", &record.task)]);
+            prompt_text(config, &prompt)
+        });
+
+        if set_task(&mut records, &id, &input) {
+            write_to_database(config, records);
+            audit::record(config, "update", std::slice::from_ref(&id));
+        }
+    }
+
+    list_todos(config);
+}
+
+/// Replaces the task text of `id`. Returns `false` when there's nothing to
+/// change: the todo is missing, or the text is blank or the same.
+fn set_task(records: &mut [Todo], id: &str, text: &str) -> bool {
+    let text = text.trim_end();
+
+    match records.iter_mut().find(|record| record.id == id) {
+        Some(record) if !text.trim().is_empty() && text != record.task => {
+            record.task = text.to_owned();
+            true
+        }
+        _ => false,
     }
-    list_todos();
 }
@@ -0,0 +1,120 @@
+//! `todosh merge <other>`: copies another list's todos into the current
+//! one, skipping those it already has.
+
+use std::{collections::HashMap, fs};
+
+use crate::{
+    Todo, audit, config::Config, error::AppError, get_reader, i18n, list_or_file, list_todos,
+    print_preview, reindex, write_to_database,
+};
+
+/// What `merge` did with the other list's todos.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    /// Added to the current list.
+    pub merged: usize,
+    /// Already present with the same completion state.
+    pub duplicates: usize,
+    /// Already present, done in one list and open in the other. These end
+    /// up completed.
+    pub conflicts: usize,
+}
+
+/// Appends the todos of `other` missing from `records`, matching by task
+/// text in any letter case. Subtasks keep their parents where the parent
+/// was merged or matched.
+pub fn merge(records: &mut Vec<Todo>, other: Vec<Todo>) -> Counts {
+    let mut counts = Counts::default();
+    let mut ids: HashMap<String, String> = HashMap::new();
+    let first_new = records.len();
+
+    for mut todo in other {
+        let key = todo.task.trim().to_lowercase();
+
+        match records
+            .iter_mut()
+            .find(|record| record.task.trim().to_lowercase() == key)
+        {
+            Some(record) => {
+                if record.completed == todo.completed {
+                    counts.duplicates += 1;
+                } else {
+                    counts.conflicts += 1;
+
+                    if !record.completed {
+                        record.completed = true;
+                        record.completed_at = todo.completed_at;
+                        record.times_completed =
+                            todo.times_completed.max(record.times_completed + 1);
+                    }
+                }
+
+                ids.insert(todo.id, record.id.clone());
+            }
+            None => {
+                counts.merged += 1;
+
+                let id = (records.len() + 1).to_string();
+                ids.insert(todo.id, id.clone());
+                todo.id = id;
+                records.push(todo);
+            }
+        }
+    }
+
+    for todo in &mut records[first_new..] {
+        todo.parent_id = ids.get(&todo.parent_id).cloned().unwrap_or_default();
+    }
+
+    counts
+}
+
+pub fn run(config: &Config, other: &str) {
+    let other_config = Config {
+        database: list_or_file(config, other),
+        ..config.clone()
+    };
+
+    if fs::canonicalize(&other_config.database).ok() == fs::canonicalize(&config.database).ok() {
+        AppError::SameDatabase {
+            path: other.to_owned(),
+        }
+        .exit(config)
+    }
+
+    let mut records: Vec<Todo> = get_reader(config).deserialize().flatten().collect();
+    let incoming: Vec<Todo> = get_reader(&other_config).deserialize().flatten().collect();
+    let first_new = records.len();
+
+    let counts = merge(&mut records, incoming);
+
+    if config.preview {
+        print_preview(config, records);
+        return;
+    }
+
+    let ids: Vec<String> = records[first_new..]
+        .iter()
+        .map(|todo| todo.id.clone())
+        .collect();
+
+    if counts.merged > 0 || counts.conflicts > 0 {
+        write_to_database(config, reindex(records));
+        audit::record(config, "merge", &ids);
+    }
+
+    list_todos(config);
+    println!(
+        "{}",
+        i18n::text(
+            config,
+            "merged",
+            &[
+                ("merged", &counts.merged),
+                ("other", &other),
+                ("duplicates", &counts.duplicates),
+                ("conflicts", &counts.conflicts),
+            ],
+        )
+    );
+}
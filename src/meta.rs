@@ -0,0 +1,131 @@
+//! A name and description for the list in use, kept in `db.meta.toml`
+//! next to `db.csv` since the CSV has nowhere to hold them.
+
+use std::{fs, path::Path, process::ExitCode};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Config, ensure_writable, error::AppError, i18n};
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ListMeta {
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+impl ListMeta {
+    /// `None` when the list has no metadata file. A file that can't be
+    /// read or parsed is reported and treated as absent.
+    pub fn load(config: &Config) -> Option<ListMeta> {
+        let path = config.meta_path();
+        let contents = fs::read_to_string(&path).ok()?;
+
+        match toml::from_str(&contents) {
+            Ok(meta) => Some(meta),
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    i18n::text(
+                        config,
+                        "unreadable-meta",
+                        &[("path", &path.display()), ("reason", &e)],
+                    )
+                );
+                None
+            }
+        }
+    }
+
+    /// The list's name, or its file name without `.csv`.
+    pub fn name_or_file(&self, config: &Config) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| file_stem(&config.database))
+    }
+
+    pub fn save(&self, config: &Config) -> Result<(), AppError> {
+        let path = config.meta_path();
+        let failed = |reason: String| AppError::WriteFailed {
+            path: path.display().to_string(),
+            reason,
+        };
+        let contents = toml::to_string(self).map_err(|e| failed(e.to_string()))?;
+
+        fs::write(&path, contents).map_err(|e| failed(e.to_string()))
+    }
+}
+
+/// The lines `list` prints above its table, when the list has metadata.
+pub fn header(config: &Config) -> Option<String> {
+    let meta = ListMeta::load(config)?;
+    let name = meta.name_or_file(config);
+
+    Some(match meta.description {
+        Some(description) => format!("{name}\n{description}"),
+        None => name,
+    })
+}
+
+/// `list-info` shows the metadata; `list-info set name <text>` and
+/// `list-info set description <text>` (or `--desc <text>`) change it.
+pub fn run(config: &Config, input: &[&str], desc: Option<&str>) -> ExitCode {
+    let mut meta = ListMeta::load(config).unwrap_or_default();
+
+    match input {
+        [] if desc.is_none() => {
+            let name = meta.name_or_file(config);
+            let description = meta.description.as_deref().unwrap_or("");
+            println!(
+                "{}",
+                i18n::text(config, "list-info-name", &[("name", &name)])
+            );
+            println!(
+                "{}",
+                i18n::text(
+                    config,
+                    "list-info-description",
+                    &[("description", &description)],
+                )
+            );
+            return ExitCode::SUCCESS;
+        }
+        [] => {}
+        ["set"] if desc.is_some() => {}
+        ["set", field, text @ ..] if !text.is_empty() => {
+            let text = text.join(" ");
+            match *field {
+                "name" => meta.name = Some(text),
+                "description" | "desc" => meta.description = Some(text),
+                _ => AppError::InvalidValue {
+                    value: (*field).to_owned(),
+                    expected: i18n::text(config, "expected-list-info-field", &[]),
+                }
+                .exit(config),
+            }
+        }
+        _ => AppError::MissingListInfo.exit(config),
+    }
+
+    if let Some(desc) = desc {
+        meta.description = Some(desc.to_owned());
+    }
+
+    ensure_writable(config);
+
+    if let Err(e) = meta.save(config) {
+        e.exit(config)
+    }
+
+    println!(
+        "{}",
+        i18n::text(config, "saved", &[("path", &config.meta_path().display())],)
+    );
+    ExitCode::SUCCESS
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .into_owned()
+}
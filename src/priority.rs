@@ -0,0 +1,85 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+/// Accepted `--priority` values, lowest first.
+pub static PRIORITIES: [&str; 3] = ["low", "medium", "high"];
+
+/// How urgent a todo is. Rows written before priorities existed, or with
+/// an empty cell, read as `Medium`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Priority {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl Priority {
+    pub fn name(self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+        }
+    }
+
+    /// One level more urgent, staying at `High`.
+    pub fn raised(self) -> Priority {
+        match self {
+            Priority::Low => Priority::Medium,
+            Priority::Medium | Priority::High => Priority::High,
+        }
+    }
+
+    /// One level less urgent, staying at `Low`.
+    pub fn lowered(self) -> Priority {
+        match self {
+            Priority::High => Priority::Medium,
+            Priority::Medium | Priority::Low => Priority::Low,
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Parses a priority name in any letter case.
+impl FromStr for Priority {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Priority, ()> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "low" => Ok(Priority::Low),
+            "medium" => Ok(Priority::Medium),
+            "high" => Ok(Priority::High),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Serialize for Priority {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Priority {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Priority, D::Error> {
+        let value = String::deserialize(deserializer)?;
+
+        if value.trim().is_empty() {
+            return Ok(Priority::default());
+        }
+
+        value.parse().map_err(|_| {
+            D::Error::custom(format!(
+                "invalid priority {value:?}, expected one of {}",
+                PRIORITIES.join(", ")
+            ))
+        })
+    }
+}
@@ -0,0 +1,164 @@
+//! What todosh remembers between runs of one database: the filters `list`
+//! was given, when `remember_list_filters` is set; when `list` last ran,
+//! for `list --new`; and what `complete` last did, for `complete --undo`.
+//! Each database keeps its own, so every named list has its own defaults.
+
+use std::{fs, path::Path};
+
+use chrono::{DateTime, Utc};
+use clap::ArgMatches;
+
+use crate::config::Config;
+
+/// Flags `list` remembers; the rest apply to one run only.
+static FLAGS: [&str; 7] = [
+    "only-incomplete",
+    "completed",
+    "all",
+    "sort",
+    "nulls",
+    "group-by",
+    "completed-first",
+];
+
+/// Prefixes of the state file lines holding when `list` last ran and
+/// the IDs `complete` last completed.
+static LAST_RUN: &str = "last-run=";
+static LAST_COMPLETED: &str = "last-completed=";
+
+/// The state file, one value per line.
+#[derive(Default)]
+struct State {
+    flags: Vec<String>,
+    last_run: Option<DateTime<Utc>>,
+    last_completed: Vec<String>,
+}
+
+/// Remembers the filters `list` was given or, when it was given none,
+/// returns the remembered ones to add to its arguments. `--default`
+/// forgets them instead.
+pub fn apply(config: &Config, args: &ArgMatches) -> Vec<String> {
+    let path = config.list_state_path();
+    let mut state = load(&path);
+
+    // Looking with --read-only doesn't change what's remembered.
+    if config.read_only {
+        return state.flags;
+    }
+
+    if args.is_present("default") {
+        state.flags.clear();
+        save(&path, &state);
+        return Vec::new();
+    }
+
+    let given = given(args);
+
+    if given.is_empty() {
+        state.flags
+    } else {
+        state.flags = given;
+        save(&path, &state);
+        Vec::new()
+    }
+}
+
+/// When `list` last ran on this database, if it ever has.
+pub fn last_run(config: &Config) -> Option<DateTime<Utc>> {
+    load(&config.list_state_path()).last_run
+}
+
+/// Notes that `list` ran `at`.
+pub fn record_run(config: &Config, at: DateTime<Utc>) {
+    update(config, |state| state.last_run = Some(at));
+}
+
+/// Notes the IDs one `complete` marked done, replacing the previous ones.
+pub fn record_completion(config: &Config, ids: &[String]) {
+    update(config, |state| state.last_completed = ids.to_vec());
+}
+
+/// The IDs the last `complete` marked done, forgetting them so the same
+/// completion can't be undone twice.
+pub fn take_completion(config: &Config) -> Vec<String> {
+    let mut ids = Vec::new();
+    update(config, |state| {
+        ids = std::mem::take(&mut state.last_completed)
+    });
+    ids
+}
+
+fn update(config: &Config, change: impl FnOnce(&mut State)) {
+    let path = config.list_state_path();
+    let mut state = load(&path);
+    change(&mut state);
+    save(&path, &state);
+}
+
+/// The remembered flags present in `args`, spelled `--name` or
+/// `--name=value`.
+fn given(args: &ArgMatches) -> Vec<String> {
+    FLAGS
+        .iter()
+        .filter(|flag| args.is_present(flag))
+        .map(|flag| match args.value_of(flag) {
+            Some(value) => format!("--{flag}={value}"),
+            None => format!("--{flag}"),
+        })
+        .collect()
+}
+
+/// Lines naming a flag `list` no longer remembers are dropped, so an
+/// edited or outdated file can't inject other options.
+fn load(path: &Path) -> State {
+    let contents = fs::read_to_string(path).unwrap_or_default();
+    let mut state = State::default();
+
+    for line in contents.lines().map(str::trim) {
+        if let Some(at) = line.strip_prefix(LAST_RUN) {
+            state.last_run = DateTime::parse_from_rfc3339(at)
+                .ok()
+                .map(|at| at.with_timezone(&Utc));
+        } else if let Some(ids) = line.strip_prefix(LAST_COMPLETED) {
+            state.last_completed = ids.split(',').map(str::to_owned).collect();
+        } else {
+            let name = line.trim_start_matches("--");
+            let name = name.split_once('=').map_or(name, |(name, _)| name);
+
+            if line.starts_with("--") && FLAGS.contains(&name) {
+                state.flags.push(line.to_owned());
+            }
+        }
+    }
+
+    state
+}
+
+/// Rewrites the state file, removing it once there's nothing to keep.
+fn save(path: &Path, state: &State) {
+    let mut contents: String = state.flags.iter().map(|flag| format!("{flag}\n")).collect();
+
+    if let Some(at) = state.last_run {
+        contents.push_str(&format!("{LAST_RUN}{}\n", at.to_rfc3339()));
+    }
+
+    if !state.last_completed.is_empty() {
+        contents.push_str(&format!(
+            "{LAST_COMPLETED}{}\n",
+            state.last_completed.join(",")
+        ));
+    }
+
+    let result = if contents.is_empty() {
+        fs::remove_file(path).or_else(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => Ok(()),
+            _ => Err(e),
+        })
+    } else {
+        fs::write(path, contents)
+    };
+
+    if let Err(e) = result {
+        eprintln!("warning: Failed to save list state: {e}");
+    }
+}
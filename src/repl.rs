@@ -0,0 +1,271 @@
+//! `todosh repl`: an interactive prompt that takes the same commands as the
+//! command line.
+//!
+//! `list` is rendered in-process with a short letter handle in front of
+//! each todo, and `complete a`, `delete b` and so on accept those handles
+//! in place of an ID for the rest of the session. Every other command runs
+//! as a separate `todosh` process against the same database, so one that
+//! fails doesn't end the session.
+
+use std::{
+    env,
+    io::{self, Write},
+    iter,
+    path::PathBuf,
+    process,
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    Line, Todo, apply_args, cli, config::Config, error::AppError, get_reader, listed_rows,
+    nest_subtasks, read_line_capped, render_todos, sort_listed,
+};
+
+/// Commands whose first input is a todo ID.
+static ID_COMMANDS: [&str; 13] = [
+    "complete",
+    "finish",
+    "uncomplete",
+    "reopen",
+    "update",
+    "delete",
+    "show",
+    "exists",
+    "add-sub",
+    "move-to",
+    "raise",
+    "lower",
+    "get",
+];
+
+/// A letter shown by the REPL's `list`. It remembers which todo it was
+/// given to, so it keeps pointing there after other todos are renumbered.
+pub struct Handle {
+    name: String,
+    id: String,
+    created_at: Option<DateTime<Utc>>,
+}
+
+pub fn run(config: &Config) {
+    let mut handles: Vec<Handle> = Vec::new();
+    let stdin = io::stdin();
+
+    loop {
+        print!("todosh> ");
+        let _ = io::stdout().flush();
+
+        let line = match read_line_capped(&mut stdin.lock(), config.max_input_bytes) {
+            Ok(Line::Text(line)) => line,
+            Ok(Line::End) => break,
+            Ok(Line::TooLong) => {
+                println!(
+                    "{}",
+                    AppError::InputTooLarge {
+                        limit: config.max_input_bytes
+                    }
+                );
+                continue;
+            }
+            Err(error) => {
+                println!(
+                    "{}",
+                    AppError::ReadInput {
+                        reason: error.to_string()
+                    }
+                );
+                break;
+            }
+        };
+
+        let mut words = split_words(&line);
+
+        match words.first().map(String::as_str) {
+            None => continue,
+            Some("exit" | "quit") => break,
+            Some("repl") => {
+                println!("{}", AppError::AlreadyInRepl);
+                continue;
+            }
+            _ => {}
+        }
+
+        let args = match cli()
+            .get_matches_from_safe(iter::once("todosh".to_owned()).chain(words.clone()))
+        {
+            Ok(args) => args,
+            Err(error) => {
+                println!("{}", error.message);
+                continue;
+            }
+        };
+
+        let command = args.value_of("command").unwrap_or(&config.default_command);
+
+        if command == "list" && !args.is_present("count-only") {
+            let mut line_config = config.clone();
+            apply_args(&mut line_config, &args);
+            let rendered;
+            (handles, rendered) = list_with_handles(&mut line_config);
+            println!("{rendered}");
+            continue;
+        }
+
+        if ID_COMMANDS.contains(&command)
+            && words.len() > 1
+            && let Some(handle) = handles.iter().find(|h| h.name == words[1])
+        {
+            match resolve_handle(config, handle) {
+                Some(id) => words[1] = id,
+                None => {
+                    println!(
+                        "{}",
+                        AppError::StaleHandle {
+                            handle: handle.name.clone()
+                        }
+                    );
+                    continue;
+                }
+            }
+        }
+
+        run_child(config, &words);
+    }
+}
+
+/// Renders `list` with a handle column, returning the handles it handed out
+/// along with the table.
+pub fn list_with_handles(config: &mut Config) -> (Vec<Handle>, String) {
+    let mut rows: Vec<Todo> = listed_rows(config, &mut get_reader(config))
+        .flatten()
+        .collect();
+    sort_listed(config, &mut rows);
+
+    let handles: Vec<Handle> = nest_subtasks(rows.clone(), config.tree)
+        .iter()
+        .enumerate()
+        .map(|(idx, todo)| Handle {
+            name: handle_name(idx),
+            id: todo.id.clone(),
+            created_at: todo.created_at,
+        })
+        .collect();
+
+    config.handles = handles
+        .iter()
+        .map(|handle| (handle.id.clone(), handle.name.clone()))
+        .collect();
+
+    let rendered = render_todos(config, rows);
+
+    (handles, rendered)
+}
+
+/// `a` to `z`, then `aa`, `ab` and so on.
+pub fn handle_name(mut idx: usize) -> String {
+    let mut name = Vec::new();
+
+    loop {
+        name.push(b'a' + (idx % 26) as u8);
+        idx /= 26;
+
+        if idx == 0 {
+            break;
+        }
+        idx -= 1;
+    }
+
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+/// The todo's current ID, found by its creation time when it has one.
+fn resolve_handle(config: &Config, handle: &Handle) -> Option<String> {
+    get_reader(config)
+        .deserialize::<Todo>()
+        .flatten()
+        .find(|todo| match handle.created_at {
+            Some(created_at) => todo.created_at == Some(created_at),
+            None => todo.id == handle.id,
+        })
+        .map(|todo| todo.id)
+}
+
+fn run_child(config: &Config, words: &[String]) {
+    let exe = match env::current_exe() {
+        Ok(exe) => exe,
+        Err(error) => {
+            println!("error: {error}");
+            return;
+        }
+    };
+
+    if let Err(error) = child_command(config, exe, words).status() {
+        println!("error: {error}");
+    }
+}
+
+/// Runs `words` with the settings the REPL was started with, passed as
+/// TODOSH_* variables so flags typed on the line still apply on top.
+pub fn child_command(config: &Config, exe: PathBuf, words: &[String]) -> process::Command {
+    let mut command = process::Command::new(exe);
+    command
+        .args(words)
+        .env("TODOSH_DB", &config.database)
+        .env("TODOSH_BACKEND", config.backend.name())
+        .env("TODOSH_DELIMITER", char::from(config.delimiter).to_string())
+        .env("TODOSH_SOURCE", "repl");
+
+    if let Some(lang) = config.lang {
+        command.env("TODOSH_LANG", lang.code());
+    }
+
+    for (name, set) in [
+        ("TODOSH_READ_ONLY", config.read_only),
+        ("TODOSH_STRICT", config.strict),
+        ("TODOSH_AUDIT", config.audit),
+        ("TODOSH_NO_IGNORE", config.no_ignore),
+    ] {
+        if set {
+            command.env(name, "1");
+        }
+    }
+
+    command
+}
+
+/// Splits a line into words like a shell would for simple input: on
+/// whitespace, with single or double quotes grouping words together.
+pub fn split_words(line: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+
+    for c in line.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+
+    if in_word {
+        words.push(word);
+    }
+
+    words
+}
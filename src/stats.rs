@@ -0,0 +1,74 @@
+use std::collections::BTreeSet;
+
+use chrono::{Days, NaiveDate, TimeDelta};
+
+use crate::{config::Config, i18n};
+
+/// Completion streaks as `(current, longest)`, in days. A streak is a run
+/// of consecutive days with at least one completion. The current streak
+/// still counts on a day nothing has been completed yet, as long as
+/// yesterday had a completion.
+pub fn streaks(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> (u32, u32) {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+
+    for &day in days.iter().filter(|&&day| day <= today) {
+        run = match previous {
+            Some(prev) if prev.checked_add_days(Days::new(1)) == Some(day) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(day);
+    }
+
+    let yesterday = today.checked_sub_days(Days::new(1));
+    let current = match previous {
+        Some(last) if last == today || Some(last) == yesterday => run,
+        _ => 0,
+    };
+
+    (current, longest)
+}
+
+/// Progress toward the daily goal, e.g. `3/5 today [###--]`. The bar is
+/// one cell per todo, scaled down to 10 cells for larger goals.
+pub fn goal_progress(config: &Config, done: usize, goal: u32) -> String {
+    let goal = goal as usize;
+    let cells = goal.min(10);
+    let filled = done.min(goal) * cells / goal.max(1);
+    let bar = format!("{}{}", "#".repeat(filled), "-".repeat(cells - filled));
+
+    let key = if done >= goal {
+        "goal-reached"
+    } else {
+        "goal-progress"
+    };
+
+    i18n::text(
+        config,
+        key,
+        &[("done", &done), ("goal", &goal), ("bar", &bar)],
+    )
+}
+
+/// Mean and median of `durations`, or `None` if there are none. With an
+/// even count the median is the mean of the two middle values.
+pub fn average_and_median(durations: &mut [TimeDelta]) -> Option<(TimeDelta, TimeDelta)> {
+    if durations.is_empty() {
+        return None;
+    }
+
+    durations.sort_unstable();
+
+    let count = durations.len() as i32;
+    let total: TimeDelta = durations.iter().sum();
+    let mid = durations.len() / 2;
+    let median = if durations.len().is_multiple_of(2) {
+        (durations[mid - 1] + durations[mid]) / 2
+    } else {
+        durations[mid]
+    };
+
+    Some((total / count, median))
+}
@@ -0,0 +1,289 @@
+//! How the database is kept on disk. Every reader parses CSV under a
+//! `COLUMNS` header, so a backend turns its file into that on the way in,
+//! and turns todos into its own format on the way out.
+//!
+//! With `encrypt` the file is sealed with ChaCha20-Poly1305 under a key
+//! stretched from a passphrase with Argon2id: `MAGIC`, the salt, the nonce,
+//! then the ciphertext. Encrypted files are opened whatever `encrypt` says,
+//! so turning it on or off takes effect on the next save.
+
+use std::{
+    env,
+    fs::File,
+    io::{self, IsTerminal, Read},
+    path::Path,
+    sync::{Mutex, OnceLock, PoisonError},
+};
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    ChaCha20Poly1305, Nonce,
+    aead::{Aead, Generate, Key, KeyInit},
+};
+use csv::WriterBuilder;
+use serde_json::{Map, Value};
+
+use crate::{
+    COLUMNS, Todo,
+    config::{Backend, Config},
+    error::AppError,
+};
+
+/// First bytes of an encrypted database.
+static MAGIC: &[u8] = b"todosh-encrypted-v1\n";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// The passphrase, asked for at most once a run.
+static PASSPHRASE: OnceLock<String> = OnceLock::new();
+
+/// The last key stretched from it, with its salt, so reading the database
+/// and saving it again only pays for Argon2 once.
+static KEY: Mutex<Option<([u8; SALT_LEN], Key<ChaCha20Poly1305>)>> = Mutex::new(None);
+
+pub trait Storage {
+    /// The database file's contents as CSV in the configured delimiter.
+    fn to_csv(&self, config: &Config, contents: Vec<u8>) -> Result<Vec<u8>, AppError>;
+
+    /// The whole database, as written to its file.
+    fn serialize(&self, config: &Config, records: &[Todo]) -> Result<Vec<u8>, String>;
+
+    /// Whether new todos can be appended to the end of the file, rather
+    /// than the file being rewritten with them.
+    fn appends(&self) -> bool;
+}
+
+/// Rows of `COLUMNS`, the default.
+pub struct CsvStorage;
+
+/// A JSON array of objects keyed by `COLUMNS`, e.g. `[{"ID": "1", ...}]`.
+/// Multi-line notes survive hand edits without CSV quoting.
+pub struct JsonStorage;
+
+/// Another backend's file, encrypted. It's decrypted by `decrypt` before
+/// anything else reads it, so only writing differs.
+pub struct EncryptedStorage(&'static (dyn Storage + Sync));
+
+static ENCRYPTED_CSV: EncryptedStorage = EncryptedStorage(&CsvStorage);
+static ENCRYPTED_JSON: EncryptedStorage = EncryptedStorage(&JsonStorage);
+
+pub fn storage(config: &Config) -> &'static dyn Storage {
+    match (config.backend, config.encrypt) {
+        (Backend::Csv, false) => &CsvStorage,
+        (Backend::Json, false) => &JsonStorage,
+        (Backend::Csv, true) => &ENCRYPTED_CSV,
+        (Backend::Json, true) => &ENCRYPTED_JSON,
+    }
+}
+
+/// Whether the file at `path` was saved encrypted.
+pub fn is_encrypted(path: &Path) -> bool {
+    let mut prefix = vec![0; MAGIC.len()];
+
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut prefix))
+        .is_ok_and(|_| prefix == MAGIC)
+}
+
+/// The plaintext of a database file: `contents` as they are, unless they
+/// were encrypted. A wrong passphrase fails Poly1305's check rather than
+/// yielding garbage.
+pub fn decrypt(config: &Config, contents: Vec<u8>) -> Result<Vec<u8>, AppError> {
+    let Some(sealed) = contents.strip_prefix(MAGIC) else {
+        return Ok(contents);
+    };
+
+    let path = config.database.display().to_string();
+    let Some((salt, body)) = split_sealed(sealed) else {
+        return Err(AppError::MalformedRow {
+            path,
+            line: None,
+            reason: "the encrypted file is truncated".to_owned(),
+        });
+    };
+
+    let (_, key) = key(config, Some(salt));
+    unseal(&key, body).ok_or(AppError::WrongPassphrase { path })
+}
+
+/// The salt after `MAGIC`, and the nonce and ciphertext after that.
+pub fn split_sealed(sealed: &[u8]) -> Option<([u8; SALT_LEN], &[u8])> {
+    let salt = sealed.get(..SALT_LEN)?.try_into().ok()?;
+    let body = sealed.get(SALT_LEN..)?;
+
+    (body.len() > NONCE_LEN).then_some((salt, body))
+}
+
+/// `plaintext` sealed under `key` with a fresh nonce, as a whole file.
+pub fn seal(key: &Key<ChaCha20Poly1305>, salt: &[u8; SALT_LEN], plaintext: &[u8]) -> Vec<u8> {
+    let nonce = Nonce::generate();
+    let ciphertext = ChaCha20Poly1305::new(key)
+        .encrypt(&nonce, plaintext)
+        .expect("ChaCha20-Poly1305 encryption of an in-memory buffer");
+
+    [MAGIC, salt, &nonce, &ciphertext].concat()
+}
+
+/// The plaintext of a nonce and ciphertext, or `None` when `key` doesn't
+/// authenticate it: a wrong passphrase or a damaged file.
+pub fn unseal(key: &Key<ChaCha20Poly1305>, body: &[u8]) -> Option<Vec<u8>> {
+    let (nonce, ciphertext) = body.split_at_checked(NONCE_LEN)?;
+    let nonce = Nonce::try_from(nonce).ok()?;
+
+    ChaCha20Poly1305::new(key).decrypt(&nonce, ciphertext).ok()
+}
+
+/// Stretches `passphrase` into a key for `salt` with Argon2id.
+pub fn derive_key(passphrase: &str, salt: &[u8; SALT_LEN]) -> Key<ChaCha20Poly1305> {
+    let mut key = Key::<ChaCha20Poly1305>::default();
+
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .expect("a 16-byte salt and 32-byte key are within Argon2's limits");
+
+    key
+}
+
+/// The key for `salt`, or when saving, `None`, the key already in use or
+/// one with a new salt.
+fn key(config: &Config, salt: Option<[u8; SALT_LEN]>) -> ([u8; SALT_LEN], Key<ChaCha20Poly1305>) {
+    let mut cached = KEY.lock().unwrap_or_else(PoisonError::into_inner);
+
+    if let Some((found, key)) = *cached
+        && salt.is_none_or(|salt| salt == found)
+    {
+        return (found, key);
+    }
+
+    // Without a salt to match, this is a new passphrase being chosen.
+    let passphrase = passphrase(config, salt.is_none());
+    let salt = salt.unwrap_or_else(<[u8; SALT_LEN]>::generate);
+    let key = derive_key(passphrase, &salt);
+
+    *cached = Some((salt, key));
+    (salt, key)
+}
+
+/// The passphrase from `TODOSH_PASSPHRASE`, or typed at the terminal
+/// without echo, twice when it's being chosen.
+fn passphrase(config: &Config, choosing: bool) -> &'static str {
+    if let Some(passphrase) = PASSPHRASE.get() {
+        return passphrase;
+    }
+
+    let path = config.database.display().to_string();
+
+    let passphrase = match env::var("TODOSH_PASSPHRASE") {
+        Ok(passphrase) if !passphrase.is_empty() => passphrase,
+        _ if io::stdin().is_terminal() => {
+            let ask = |prompt: &str| {
+                let answer = rpassword::prompt_password(prompt).unwrap_or_default();
+
+                if answer.is_empty() {
+                    AppError::MissingPassphrase { path: path.clone() }.exit(config)
+                }
+
+                answer
+            };
+
+            let passphrase = ask(&format!("Passphrase for {path}: "));
+
+            if choosing && ask("Repeat the passphrase: ") != passphrase {
+                AppError::PassphraseMismatch.exit(config)
+            }
+
+            passphrase
+        }
+        _ => AppError::MissingPassphrase { path }.exit(config),
+    };
+
+    PASSPHRASE.get_or_init(|| passphrase)
+}
+
+impl Storage for CsvStorage {
+    fn to_csv(&self, _config: &Config, contents: Vec<u8>) -> Result<Vec<u8>, AppError> {
+        Ok(contents)
+    }
+
+    fn serialize(&self, config: &Config, records: &[Todo]) -> Result<Vec<u8>, String> {
+        let mut writer = WriterBuilder::new()
+            .delimiter(config.delimiter)
+            .has_headers(true)
+            .from_writer(Vec::new());
+
+        for todo in records {
+            writer.serialize(todo).map_err(|e| e.to_string())?;
+        }
+
+        writer.into_inner().map_err(|e| e.to_string())
+    }
+
+    fn appends(&self) -> bool {
+        true
+    }
+}
+
+impl Storage for JsonStorage {
+    fn to_csv(&self, config: &Config, contents: Vec<u8>) -> Result<Vec<u8>, AppError> {
+        let malformed = |line: Option<u64>, reason: String| AppError::MalformedRow {
+            path: config.database.display().to_string(),
+            line,
+            reason,
+        };
+
+        // A newly created database is an empty file.
+        let objects: Vec<Map<String, Value>> = if contents.trim_ascii().is_empty() {
+            Vec::new()
+        } else {
+            serde_json::from_slice(&contents)
+                .map_err(|e| malformed(None, format!("invalid JSON: {e}")))?
+        };
+
+        let mut writer = WriterBuilder::new()
+            .delimiter(config.delimiter)
+            .from_writer(Vec::new());
+        let write_error = |e: csv::Error| malformed(None, e.to_string());
+
+        writer.write_record(COLUMNS).map_err(write_error)?;
+
+        for object in &objects {
+            let cells = COLUMNS.iter().map(|column| match object.get(*column) {
+                None | Some(Value::Null) => String::new(),
+                Some(Value::String(text)) => text.clone(),
+                Some(other) => other.to_string(),
+            });
+            writer.write_record(cells).map_err(write_error)?;
+        }
+
+        writer
+            .into_inner()
+            .map_err(|e| malformed(None, e.to_string()))
+    }
+
+    fn serialize(&self, _config: &Config, records: &[Todo]) -> Result<Vec<u8>, String> {
+        let mut json = serde_json::to_vec_pretty(records).map_err(|e| e.to_string())?;
+        json.push(b'\n');
+        Ok(json)
+    }
+
+    fn appends(&self) -> bool {
+        false
+    }
+}
+
+impl Storage for EncryptedStorage {
+    fn to_csv(&self, config: &Config, contents: Vec<u8>) -> Result<Vec<u8>, AppError> {
+        self.0.to_csv(config, contents)
+    }
+
+    fn serialize(&self, config: &Config, records: &[Todo]) -> Result<Vec<u8>, String> {
+        let plaintext = self.0.serialize(config, records)?;
+        let (salt, key) = key(config, None);
+
+        Ok(seal(&key, &salt, &plaintext))
+    }
+
+    fn appends(&self) -> bool {
+        false
+    }
+}
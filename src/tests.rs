@@ -0,0 +1,2003 @@
+use tempfile::TempDir;
+
+use super::*;
+
+/// A config pointing at a fresh, empty database inside a temp dir. The
+/// dir is removed when the returned guard is dropped.
+fn temp_config() -> (TempDir, Config) {
+    let dir = TempDir::new().unwrap();
+    let config = Config {
+        database: dir.path().join("db.csv"),
+        ..Config::default()
+    };
+
+    create_db_if_not_exists(&config);
+
+    (dir, config)
+}
+
+fn read_todos(config: &Config) -> Vec<Todo> {
+    get_reader(config)
+        .deserialize()
+        .collect::<csv::Result<_>>()
+        .unwrap()
+}
+
+fn add(config: &Config, task: &str) {
+    create_todo(config, Todo::new(0, task), false);
+}
+
+fn tasks(config: &Config) -> Vec<(String, String)> {
+    read_todos(config)
+        .into_iter()
+        .map(|todo| (todo.id, todo.task))
+        .collect()
+}
+
+#[test]
+fn create_list_complete_delete_round_trip() {
+    let (_dir, config) = temp_config();
+
+    add(&config, "Take out trash");
+    add(&config, "Cook dinner");
+
+    let listed = render_list(&config);
+    assert!(listed.contains("Take out trash"));
+    assert!(listed.contains("Cook dinner"));
+
+    complete_todo(&config, "2".to_owned(), Some("done early"));
+
+    let todos = read_todos(&config);
+    assert!(!todos[0].completed);
+    assert!(todos[1].completed);
+    assert!(todos[1].completed_at.is_some());
+    assert_eq!(todos[1].notes, "done early");
+
+    delete_todo(&config, "1".to_owned());
+
+    assert_eq!(tasks(&config), [("1".to_owned(), "Cook dinner".to_owned())]);
+    assert!(read_todos(&config)[0].completed);
+}
+
+#[test]
+fn delete_reindexes_remaining_todos() {
+    let (_dir, config) = temp_config();
+
+    for task in ["one", "two", "three", "four"] {
+        add(&config, task);
+    }
+
+    delete_todo(&config, "2".to_owned());
+
+    assert_eq!(
+        tasks(&config),
+        [
+            ("1".to_owned(), "one".to_owned()),
+            ("2".to_owned(), "three".to_owned()),
+            ("3".to_owned(), "four".to_owned()),
+        ]
+    );
+
+    add(&config, "five");
+    assert_eq!(tasks(&config).last().unwrap().0, "4");
+}
+
+#[test]
+fn ids_past_the_u16_range_parse_and_delete() {
+    let (_dir, config) = temp_config();
+    let rows = u64::from(u16::MAX) + 2;
+
+    let mut csv = format!("{}\n", COLUMNS.join(","));
+    for id in 1..=rows {
+        csv.push_str(&format!("{id},Task {id},false,,,,,,,,,,,\n"));
+    }
+    fs::write(&config.database, csv).unwrap();
+
+    assert_eq!(parse_id(&config, "65536"), 65536);
+    assert_eq!(parse_id(&config, "18446744073709551615"), u64::MAX);
+
+    delete_todo(&config, "65536".to_owned());
+
+    let todos = read_todos(&config);
+    assert_eq!(todos.len() as u64, rows - 1);
+    assert_eq!(todos[65534].task, "Task 65535");
+    assert_eq!(todos[65535].id, "65536");
+    assert_eq!(todos[65535].task, "Task 65537");
+}
+
+#[test]
+fn unquoted_multi_word_input_is_kept_intact() {
+    let (_dir, config) = temp_config();
+
+    let args = cli().get_matches_from(["todosh", "create", "buy", "milk", "and", "eggs"]);
+    let task = input_text(&args, 0).unwrap();
+    add(&config, &task);
+
+    let args = cli().get_matches_from(["todosh", "update", "1", "buy", "oat", "milk"]);
+    assert_eq!(args.value_of("input"), Some("1"));
+    update_todo(&config, "1".to_owned(), input_text(&args, 1));
+
+    assert_eq!(
+        tasks(&config),
+        [("1".to_owned(), "buy oat milk".to_owned())]
+    );
+}
+
+#[test]
+fn last_resolves_to_the_highest_id() {
+    let (_dir, config) = temp_config();
+
+    for task in ["one", "two", "three"] {
+        add(&config, task);
+    }
+
+    assert_eq!(resolve_id(&config, "last"), 3);
+    assert_eq!(resolve_id(&config, "2"), 2);
+}
+
+#[test]
+fn negative_ids_count_back_from_the_end() {
+    let (_dir, config) = temp_config();
+
+    for task in ["one", "two", "three"] {
+        add(&config, task);
+    }
+
+    assert_eq!(resolve_id(&config, "-1"), 3);
+    assert_eq!(resolve_id(&config, "-3"), 1);
+
+    let args = cli().get_matches_from(["todosh", "delete", "-2"]);
+    assert_eq!(args.value_of("input"), Some("-2"));
+}
+
+#[test]
+fn completed_accepts_common_boolean_spellings() {
+    let (_dir, config) = temp_config();
+    let forms = [
+        ("true", true),
+        ("TRUE", true),
+        ("yes", true),
+        ("Y", true),
+        ("1", true),
+        ("false", false),
+        ("False", false),
+        ("no", false),
+        ("n", false),
+        ("0", false),
+    ];
+
+    let mut csv = String::from("ID,TASK,COMPLETED\n");
+    for (id, (form, _)) in forms.iter().enumerate() {
+        csv.push_str(&format!("{},task,{form}\n", id + 1));
+    }
+    fs::write(&config.database, csv).unwrap();
+
+    let completed: Vec<bool> = read_todos(&config).iter().map(|t| t.completed).collect();
+    let expected: Vec<bool> = forms.iter().map(|(_, value)| *value).collect();
+    assert_eq!(completed, expected);
+
+    complete_todo(&config, "10".to_owned(), None);
+
+    let written = fs::read_to_string(&config.database).unwrap();
+    assert!(written.lines().skip(1).all(|line| {
+        let completed = line.split(',').nth(2).unwrap();
+        completed == "true" || completed == "false"
+    }));
+}
+
+#[test]
+fn blank_completed_cell_reads_as_pending() {
+    let (_dir, config) = temp_config();
+    fs::write(
+        &config.database,
+        "ID,TASK,COMPLETED\n1,Hand edited,\n2,Padded, \n3,Done,true\n",
+    )
+    .unwrap();
+
+    let completed: Vec<bool> = read_todos(&config).iter().map(|t| t.completed).collect();
+    assert_eq!(completed, [false, false, true]);
+    assert!(render_list(&config).contains("Hand edited"));
+}
+
+#[test]
+fn completed_rejects_unknown_values() {
+    let (_dir, config) = temp_config();
+    fs::write(&config.database, "ID,TASK,COMPLETED\n1,task,maybe\n").unwrap();
+
+    assert!(
+        get_reader(&config)
+            .deserialize::<Todo>()
+            .next()
+            .unwrap()
+            .is_err()
+    );
+}
+
+#[test]
+fn list_reads_a_consistent_snapshot_while_another_writer_rewrites() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let (_dir, config) = temp_config();
+    add(&config, "Seed");
+    let writing = AtomicBool::new(true);
+
+    std::thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                while writing.load(Ordering::Relaxed) {
+                    assert!(!read_todos(&config).is_empty());
+                    assert!(render_list(&config).contains("Seed"));
+                }
+            });
+        }
+
+        for n in 0..200 {
+            create_todo(&config, Todo::new(0, &format!("Task {n}")), true);
+        }
+        writing.store(false, Ordering::Relaxed);
+    });
+
+    assert_eq!(read_todos(&config).len(), 201);
+}
+
+#[test]
+fn tree_draws_connectors_and_survives_parent_cycles() {
+    let todo = |id: TodoId, parent: &str| Todo {
+        parent_id: parent.to_owned(),
+        ..Todo::new(id, &format!("t{id}"))
+    };
+    let todos = vec![
+        todo(1, ""),
+        todo(2, "1"),
+        todo(3, "2"),
+        todo(4, "1"),
+        todo(5, ""),
+        todo(6, "7"),
+        todo(7, "6"),
+    ];
+
+    let tasks: Vec<String> = nest_subtasks(todos, true)
+        .into_iter()
+        .map(|todo| todo.task)
+        .collect();
+    assert_eq!(
+        tasks,
+        ["t1", "├─ t2", "│  └─ t3", "└─ t4", "t5", "t6", "└─ t7"]
+    );
+}
+
+#[test]
+fn subtasks_nest_under_their_parent_and_derive_its_completion() {
+    let (_dir, config) = temp_config();
+
+    add(&config, "Move house");
+    add(&config, "Other");
+    add_subtask(&config, "1", Todo::new(0, "Pack boxes"));
+    add_subtask(&config, "3", Todo::new(0, "Buy tape"));
+
+    let tasks: Vec<String> = nest_subtasks(read_todos(&config), false)
+        .into_iter()
+        .map(|todo| todo.task)
+        .collect();
+    assert_eq!(
+        tasks,
+        ["Move house", "└ Pack boxes", "  └ Buy tape", "Other"]
+    );
+
+    complete_todo(&config, "4".to_owned(), None);
+    let todos = read_todos(&config);
+    assert!(todos[0].completed && todos[2].completed);
+    assert!(!todos[1].completed);
+
+    add_subtask(&config, "1", Todo::new(0, "Book van"));
+    assert!(!read_todos(&config)[0].completed);
+
+    delete_todo(&config, "1".to_owned());
+    let parents: Vec<(String, String)> = read_todos(&config)
+        .into_iter()
+        .map(|todo| (todo.id, todo.parent_id))
+        .collect();
+    let expected = [("1", ""), ("2", ""), ("3", "2"), ("4", "")];
+    assert_eq!(parents, expected.map(|(a, b)| (a.to_owned(), b.to_owned())));
+}
+
+#[test]
+fn completed_at_sorts_newest_first_with_undated_last() {
+    let at = |day: i64| Some(DateTime::<Utc>::UNIX_EPOCH + TimeDelta::try_days(day).unwrap());
+    let todo = |id, completed_at| Todo {
+        completed: true,
+        completed_at,
+        ..Todo::new(id, "task")
+    };
+    let mut todos = vec![
+        todo(1, at(3)),
+        todo(2, None),
+        todo(3, at(9)),
+        todo(4, at(5)),
+    ];
+
+    let config = Config {
+        sort: SortBy::parse_spec("completed_at").unwrap(),
+        ..Config::default()
+    };
+    sort_listed(&config, &mut todos);
+
+    let ids: Vec<&str> = todos.iter().map(|todo| todo.id.as_str()).collect();
+    assert_eq!(ids, ["3", "4", "1", "2"]);
+
+    let archived = cli().get_matches_from(["todosh", "list", "--archived"]);
+    let mut config = Config::default();
+    apply_args(&mut config, &archived);
+    assert_eq!(config.sort, [SortBy::CompletedAt]);
+}
+
+#[cfg(unix)]
+#[test]
+fn read_only_database_directory_is_reported_as_not_writable() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let (dir, _config) = temp_config();
+    assert!(dir_is_writable(dir.path()));
+
+    fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o555)).unwrap();
+
+    // Root ignores directory permissions, so only check when they apply.
+    if File::create(dir.path().join("probe")).is_err() {
+        assert!(!dir_is_writable(dir.path()));
+    }
+
+    fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+#[test]
+fn streaks_count_consecutive_completion_days() {
+    let day = |d| NaiveDate::from_ymd_opt(2026, 3, d).unwrap();
+    let days: BTreeSet<NaiveDate> = [1, 2, 3, 4, 7, 8, 9].map(day).into();
+
+    assert_eq!(stats::streaks(&days, day(9)), (3, 4));
+    assert_eq!(stats::streaks(&days, day(10)), (3, 4));
+    assert_eq!(stats::streaks(&days, day(11)), (0, 4));
+    assert_eq!(stats::streaks(&BTreeSet::new(), day(11)), (0, 0));
+}
+
+#[test]
+fn complete_preview_leaves_the_database_untouched() {
+    let (_dir, mut config) = temp_config();
+    add(&config, "Preview me");
+    let before = fs::read(&config.database).unwrap();
+
+    config.preview = true;
+    complete_todo(&config, "1".to_owned(), Some("not saved"));
+
+    assert_eq!(fs::read(&config.database).unwrap(), before);
+}
+
+#[test]
+fn ignore_patterns_match_task_text_and_tags() {
+    let (dir, config) = temp_config();
+    let path = dir.path().join("db.todoignore");
+    fs::write(&path, "# someday items\nsomeday\nread * book\n\n").unwrap();
+    let ignore = IgnoreList::load(&path);
+
+    assert!(ignore.matches("Learn guitar", ["music", "Someday"].into_iter()));
+    assert!(ignore.matches("Read a book", [].into_iter()));
+    assert!(!ignore.matches("Read a book today", [].into_iter()));
+    assert!(!ignore.matches("Buy milk", ["errands"].into_iter()));
+
+    add(&config, "Plan someday trip");
+    add(&config, "Buy milk");
+    let config = Config { ignore, ..config };
+    assert!(!render_list(&config).contains("someday"));
+}
+
+#[test]
+fn move_to_appends_to_the_target_list_and_reindexes_the_source() {
+    let (_dir, config) = temp_config();
+    add(&config, "Email boss");
+    add(&config, "Water plants");
+    add(&config, "Book flights");
+
+    move_todo(&config, "2", "home");
+
+    let home = Config {
+        database: config.list_path("home").unwrap(),
+        ..config.clone()
+    };
+    assert_eq!(tasks(&home), [("1".to_owned(), "Water plants".to_owned())]);
+    assert_eq!(
+        tasks(&config),
+        [
+            ("1".to_owned(), "Email boss".to_owned()),
+            ("2".to_owned(), "Book flights".to_owned())
+        ]
+    );
+    assert_eq!(config.list_path("../elsewhere"), None);
+}
+
+#[test]
+fn strict_reports_the_first_malformed_line() {
+    let (_dir, config) = temp_config();
+    let check = |rows: &str| {
+        fs::write(&config.database, format!("{}\n{rows}", COLUMNS.join(","))).unwrap();
+        first_malformed(&config, get_file_reader(&config)).map(|e| e.to_string())
+    };
+
+    assert_eq!(check("1,ok,false,,,,,,,,,,,\n2,ok,,,,,,,,,,,,\n"), None);
+    assert_eq!(
+        check("1,ok,false,,,,,,,,,,,\n2,bad,maybe,,,,,,,,,,,\n3,short\n").unwrap(),
+        format!(
+            "error: {} line 3: invalid COMPLETED value \"maybe\"",
+            config.database.display()
+        )
+    );
+    assert!(
+        check("1,short\n")
+            .unwrap()
+            .contains("line 2: found 2 field(s), expected 14")
+    );
+}
+
+#[test]
+fn invalid_utf8_is_read_lossily_and_repaired() {
+    let (_dir, config) = temp_config();
+    let mut csv = format!("{}\n", COLUMNS.join(",")).into_bytes();
+    csv.extend_from_slice(b"1,Caf\xe9 run,false,,,,,,,,,,,\n2,Buy milk,false,,,,,,,,,,,\n");
+    fs::write(&config.database, csv).unwrap();
+
+    assert_eq!(
+        tasks(&config),
+        [
+            ("1".to_owned(), "Caf\u{FFFD} run".to_owned()),
+            ("2".to_owned(), "Buy milk".to_owned())
+        ]
+    );
+
+    repair_database(&config);
+
+    let repaired = String::from_utf8(fs::read(&config.database).unwrap()).unwrap();
+    assert!(repaired.contains("Caf\u{FFFD} run"));
+}
+
+#[test]
+fn repl_handles_and_words() {
+    let names: Vec<String> = [0, 1, 25, 26, 27, 701, 702].map(repl::handle_name).into();
+    assert_eq!(names, ["a", "b", "z", "aa", "ab", "zz", "aaa"]);
+
+    assert_eq!(
+        repl::split_words("create \"buy milk\" --tag 'home life'\n"),
+        ["create", "buy milk", "--tag", "home life"]
+    );
+}
+
+/// The config a command run by the REPL ends up with, given the REPL's own
+/// and the words typed.
+fn repl_child_config(repl_config: &Config, words: &[&str]) -> Config {
+    let words: Vec<String> = words.iter().map(|word| word.to_string()).collect();
+    let command = repl::child_command(repl_config, PathBuf::from("todosh"), &words);
+    let vars: Vec<(String, OsString)> = command
+        .get_envs()
+        .filter_map(|(name, value)| Some((name.to_str()?.to_owned(), value?.to_owned())))
+        .collect();
+
+    let mut config = Config::default();
+    config.apply_env(|name| {
+        vars.iter()
+            .find(|(var, _)| var == name)
+            .map(|(_, value)| value.clone())
+    });
+    apply_args(
+        &mut config,
+        &cli().get_matches_from(std::iter::once("todosh".to_owned()).chain(words)),
+    );
+
+    config
+}
+
+#[test]
+fn repl_commands_stay_read_only() {
+    let (_dir, config) = temp_config();
+    let repl_config = Config {
+        read_only: true,
+        ..config.clone()
+    };
+
+    let child = repl_child_config(&repl_config, &["delete", "1"]);
+    assert_eq!(child.database, config.database);
+    assert!(child.read_only);
+    assert!(!repl_child_config(&config, &["delete", "1"]).read_only);
+}
+
+#[test]
+fn repl_commands_keep_the_repl_settings() {
+    let (_dir, config) = temp_config();
+    let repl_config = Config {
+        backend: Backend::Json,
+        delimiter: b';',
+        lang: Some(Lang::De),
+        strict: true,
+        audit: true,
+        no_ignore: true,
+        ..config.clone()
+    };
+
+    let child = repl_child_config(&repl_config, &["complete", "1"]);
+    assert_eq!(child.backend, Backend::Json);
+    assert_eq!(child.delimiter, b';');
+    assert_eq!(child.lang, Some(Lang::De));
+    assert!(child.strict && child.audit && child.no_ignore);
+
+    let child = repl_child_config(&repl_config, &["--lang", "en", "list"]);
+    assert_eq!(child.lang, Some(Lang::En));
+}
+
+#[test]
+fn repl_list_keeps_newest_order() {
+    let (_dir, mut config) = temp_config();
+    let created: DateTime<Utc> = "2026-03-01T00:00:00Z".parse().unwrap();
+    let todos = ["first", "second", "third"]
+        .into_iter()
+        .zip(1..)
+        .map(|(task, id)| Todo {
+            created_at: Some(created + TimeDelta::try_hours(id).unwrap()),
+            ..Todo::new(id as TodoId, task)
+        })
+        .collect();
+    write_to_database(&config, todos);
+
+    config.newest = true;
+    let (_, rendered) = repl::list_with_handles(&mut config);
+
+    let row = |task| rendered.find(task).unwrap();
+    assert!(row("third") < row("second") && row("second") < row("first"));
+    assert_eq!(config.handles["3"], "a");
+}
+
+#[test]
+fn run_applies_a_script_and_saves_once() {
+    let (dir, config) = temp_config();
+    let script = dir.path().join("commands.txt");
+    fs::write(
+        &script,
+        "# morning\ncreate \"Buy milk\"\ncreate Walk dog\n\ncomplete 1\ndelete 7\nupdate last Walk the dog\n",
+    )
+    .unwrap();
+
+    let code = batch::run(&config, script.to_str().unwrap());
+
+    assert_eq!(code, ExitCode::FAILURE);
+    let todos = read_todos(&config);
+    assert_eq!(todos.len(), 2);
+    assert!(todos[0].completed);
+    assert_eq!(todos[1].task, "Walk the dog");
+}
+
+#[test]
+fn run_keeps_to_one_database_and_allows_unchanged_updates() {
+    let (dir, config) = temp_config();
+    let script = dir.path().join("commands.txt");
+    let run = |lines: &str| {
+        fs::write(&script, lines).unwrap();
+        batch::run(&config, script.to_str().unwrap())
+    };
+
+    assert_eq!(
+        run("create Buy milk\nupdate 1 Buy milk\n"),
+        ExitCode::SUCCESS
+    );
+    assert_eq!(run("update 1 \"  \"\n"), ExitCode::FAILURE);
+
+    let other = dir.path().join("other.csv");
+    let line = format!("create elsewhere --db {}\n", other.display());
+    assert_eq!(run(&line), ExitCode::FAILURE);
+    assert_eq!(run("list --archived\n"), ExitCode::FAILURE);
+    assert!(!other.exists());
+    assert_eq!(tasks(&config), [("1".to_owned(), "Buy milk".to_owned())]);
+}
+
+#[test]
+fn run_completes_like_complete() {
+    let (dir, config) = temp_config();
+    let script = dir.path().join("commands.txt");
+    let run = |config: &Config, lines: &str| {
+        fs::write(&script, lines).unwrap();
+        batch::run(config, script.to_str().unwrap())
+    };
+    let archiving = Config {
+        auto_archive_on_complete: true,
+        ..config.clone()
+    };
+
+    assert_eq!(
+        run(&archiving, "create Pay rent\ncreate Call mum\ncomplete 1\n"),
+        ExitCode::SUCCESS
+    );
+    assert_eq!(tasks(&config), [("1".to_owned(), "Call mum".to_owned())]);
+    let archive = Config {
+        database: config.archive_path(),
+        ..config.clone()
+    };
+    assert_eq!(tasks(&archive), [("1".to_owned(), "Pay rent".to_owned())]);
+    assert!(remember::take_completion(&config).is_empty());
+
+    assert_eq!(run(&config, "complete 1\n"), ExitCode::SUCCESS);
+    assert_eq!(remember::take_completion(&config), ["1"]);
+    assert_eq!(run(&config, "complete 1\n"), ExitCode::FAILURE);
+}
+
+#[test]
+fn list_completed_shows_only_done_todos() {
+    let (_dir, config) = temp_config();
+    add(&config, "Pending task");
+    add(&config, "Done task");
+    complete_todo(&config, "2".to_owned(), None);
+
+    let listed = |flags: &[&str], hide_completed| {
+        let args = cli().get_matches_from(["todosh", "list"].iter().chain(flags));
+        let mut config = Config {
+            only_incomplete: hide_completed,
+            ..config.clone()
+        };
+        apply_args(&mut config, &args);
+        render_list(&config)
+    };
+
+    let done = listed(&["--completed"], true);
+    assert!(done.contains("Done task") && !done.contains("Pending task"));
+
+    let pending = listed(&[], true);
+    assert!(pending.contains("Pending task") && !pending.contains("Done task"));
+
+    let all = listed(&["--all"], true);
+    assert!(all.contains("Pending task") && all.contains("Done task"));
+}
+
+#[test]
+fn utc_timestamps_render_in_a_non_utc_offset() {
+    use timezone::Timezone;
+
+    let stored: DateTime<Utc> = "2026-03-01T22:45:00Z".parse().unwrap();
+
+    let kolkata = Timezone::parse("+05:30").unwrap();
+    assert_eq!(kolkata.render(stored), "2026-03-02 04:15:00 +05:30");
+
+    let new_york = Timezone::parse("-0500").unwrap();
+    assert_eq!(new_york.render(stored), "2026-03-01 17:45:00 -05:00");
+
+    assert_eq!(
+        Timezone::parse("UTC").unwrap().render(stored),
+        "2026-03-01 22:45:00 +00:00"
+    );
+    assert_eq!(Timezone::parse("Mars/Olympus"), None);
+}
+
+#[test]
+fn porcelain_lines_keep_three_tab_separated_fields() {
+    let mut todo = Todo::new(4, "Pay\trent\nC:\\bills");
+    todo.completed = true;
+
+    assert_eq!(porcelain_line(&todo), "4\ttrue\tPay\\trent\\nC:\\\\bills");
+    assert_eq!(
+        porcelain_line(&Todo::new(1, "Buy milk")),
+        "1\tfalse\tBuy milk"
+    );
+}
+
+#[test]
+fn completion_time_average_and_median_skip_missing_timestamps() {
+    let hours = |h| TimeDelta::try_hours(h).unwrap();
+    let created: DateTime<Utc> = "2026-03-01T00:00:00Z".parse().unwrap();
+
+    let todos: Vec<Todo> = [Some(2), Some(4), Some(12), None]
+        .into_iter()
+        .map(|open| {
+            let mut todo = Todo::new(1, "task");
+            todo.created_at = Some(created);
+            todo.completed = true;
+            todo.completed_at = open.map(|h| created + hours(h));
+            todo
+        })
+        .collect();
+
+    let mut durations: Vec<TimeDelta> = todos.iter().filter_map(|t| t.age(Utc::now())).collect();
+    assert_eq!(
+        stats::average_and_median(&mut durations),
+        Some((hours(6), hours(4)))
+    );
+
+    durations.pop();
+    assert_eq!(
+        stats::average_and_median(&mut durations),
+        Some((hours(3), hours(3)))
+    );
+    assert_eq!(stats::average_and_median(&mut []), None);
+}
+
+#[test]
+fn semicolon_delimited_database_round_trips() {
+    let (_dir, config) = temp_config();
+    let config = Config {
+        delimiter: b';',
+        database: config.database.with_file_name("semicolons.csv"),
+        ..config
+    };
+    create_db_if_not_exists(&config);
+
+    add(&config, "Buy eggs; milk, and bread");
+    add(&config, "Call mum");
+    complete_todo(&config, "2".to_owned(), None);
+
+    let contents = fs::read_to_string(&config.database).unwrap();
+    assert!(contents.starts_with(&COLUMNS.join(";")));
+    let todos = read_todos(&config);
+    assert_eq!(todos[0].task, "Buy eggs; milk, and bread");
+    assert!(!todos[0].completed && todos[1].completed);
+    assert!(render_todos(&config, todos).contains("Buy eggs; milk, and bread"));
+
+    // A comma-delimited file, read as if it were split on `;`.
+    let (_commas_dir, commas) = temp_config();
+    add(&commas, "Buy eggs");
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(b';')
+        .from_path(&commas.database)
+        .unwrap();
+    let headers = reader.headers().unwrap();
+    assert!(delimiter_mismatch(&commas, headers).is_none());
+    assert!(matches!(
+        delimiter_mismatch(&config, headers),
+        Some(AppError::MalformedRow { line: Some(1), .. })
+    ));
+}
+
+#[test]
+fn tasks_with_delimiters_quotes_and_newlines_round_trip() {
+    let (_dir, config) = temp_config();
+    let texts = [
+        "Buy eggs, milk, and bread",
+        "Read \"The Rust Book\"",
+        "Line one\nline two",
+        "Ends with a quote\"",
+        "\"Quoted, with comma\"\nand a newline\n",
+    ];
+
+    for text in texts {
+        add(&config, text);
+    }
+
+    let note = "First line\n\n  indented, \"quoted\" line\n";
+    complete_todo(&config, "1".to_owned(), Some(note));
+
+    let todos = read_todos(&config);
+    let stored: Vec<&str> = todos.iter().map(|todo| todo.task.as_str()).collect();
+    assert_eq!(stored, texts);
+    assert_eq!(todos[0].notes, note);
+    assert_eq!(field_value(&config, &todos[4], "task"), texts[4]);
+
+    let listed = render_todos(
+        &Config {
+            format: OutputFormat::Json,
+            ..config.clone()
+        },
+        todos,
+    );
+    let listed: Vec<serde_json::Value> = serde_json::from_str(&listed).unwrap();
+    assert_eq!(listed[1]["TASK"], texts[1]);
+}
+
+#[test]
+fn completion_symbols_replace_true_and_false_in_list() {
+    let (_dir, config) = temp_config();
+    add(&config, "Done");
+    add(&config, "Pending");
+    complete_todo(&config, "1".to_owned(), None);
+
+    let config = Config {
+        completed_symbol: "[x]".to_owned(),
+        pending_symbol: "[ ]".to_owned(),
+        ..config
+    };
+    let listed = render_list(&config);
+    assert!(listed.contains("│ [x]") && listed.contains("│ [ ]"));
+    assert!(!listed.contains("true") && !listed.contains("false"));
+
+    assert!(config::validate_symbol("✅").is_ok());
+    assert!(config::validate_symbol("").is_err());
+    assert!(config::validate_symbol("two\nlines").is_err());
+}
+
+#[test]
+fn reopen_flips_the_most_recently_completed_todo() {
+    let (_dir, config) = temp_config();
+    for task in ["one", "two", "three"] {
+        add(&config, task);
+    }
+    complete_todo(&config, "3".to_owned(), None);
+    complete_todo(&config, "1".to_owned(), None);
+
+    reopen_todo(&config, None);
+
+    let todos = read_todos(&config);
+    assert!(!todos[0].completed && todos[0].completed_at.is_none());
+    assert!(todos[2].completed);
+    assert_eq!(last_completed(&todos).as_deref(), Some("3"));
+
+    let mut records = todos;
+    assert!(matches!(
+        mark_pending(&mut records, "2"),
+        Err(AppError::NotCompleted { .. })
+    ));
+}
+
+#[test]
+fn prompts_see_end_of_input_on_empty_stdin() {
+    let config = Config::default();
+    assert_eq!(read_answer(&config, &mut io::empty()), None);
+    assert_eq!(
+        read_answer(&config, &mut "\n".as_bytes()).as_deref(),
+        Some("")
+    );
+    assert_eq!(
+        read_answer(&config, &mut "buy milk\r\n".as_bytes()).as_deref(),
+        Some("buy milk")
+    );
+}
+
+#[test]
+fn capped_reads_skip_overlong_lines() {
+    let mut input = "12345\n123456\nnext\n".as_bytes();
+    let mut read = || match read_line_capped(&mut input, 5).unwrap() {
+        Line::Text(text) => Some(text),
+        Line::TooLong => None,
+        Line::End => Some("<end>".to_owned()),
+    };
+
+    assert_eq!(read().as_deref(), Some("12345\n"));
+    assert_eq!(read(), None);
+    assert_eq!(read().as_deref(), Some("next\n"));
+    assert_eq!(read().as_deref(), Some("<end>"));
+}
+
+#[test]
+fn diff_matches_todos_across_renumbering() {
+    let at = |n: i64| Some(DateTime::<Utc>::UNIX_EPOCH + TimeDelta::try_hours(n).unwrap());
+    let todo = |id, task: &str, created_at, completed| Todo {
+        created_at,
+        completed,
+        ..Todo::new(id, task)
+    };
+
+    let old = [
+        todo(1, "Write report", at(1), false),
+        todo(2, "Call bank", at(2), false),
+        todo(3, "Pay rent", at(3), false),
+        todo(4, "Legacy row", None, false),
+    ];
+    let new = [
+        todo(1, "Write the report", at(1), true),
+        todo(2, "Pay rent", at(3), false),
+        todo(3, "Legacy row", None, false),
+        todo(4, "Buy milk", at(5), false),
+    ];
+
+    let changes: Vec<String> = diff::changes(&old, &new)
+        .iter()
+        .map(|change| match change {
+            diff::Change::Added(t) => format!("+{}", t.task),
+            diff::Change::Removed(t) => format!("-{}", t.task),
+            diff::Change::Completed(t) => format!("x{}", t.task),
+            diff::Change::Reopened(t) => format!("o{}", t.task),
+            diff::Change::Retitled { old, new } => format!("~{}>{}", old.task, new.task),
+        })
+        .collect();
+
+    assert_eq!(
+        changes,
+        [
+            "~Write report>Write the report",
+            "xWrite the report",
+            "+Buy milk",
+            "-Call bank"
+        ]
+    );
+}
+
+#[test]
+fn max_active_limits_open_todos_unless_forced() {
+    let mut config = Config {
+        max_active: 3,
+        ..Config::default()
+    };
+
+    assert!(check_active_limit(&config, 2, 1).is_ok());
+    assert_eq!(
+        check_active_limit(&config, 3, 1).unwrap_err().to_string(),
+        "error: 3 todos are already open and max_active is 3; \
+         complete or delete one first, or pass --force"
+    );
+    assert!(check_active_limit(&config, 2, 2).is_err());
+
+    config.force = true;
+    assert!(check_active_limit(&config, 3, 1).is_ok());
+
+    config.max_active = 0;
+    config.force = false;
+    assert!(check_active_limit(&config, 100, 1).is_ok());
+}
+
+#[test]
+fn log_records_a_completed_todo_with_create_metadata() {
+    let (_dir, config) = temp_config();
+    let config = Config {
+        max_active: 1,
+        ..config
+    };
+    add(&config, "Open");
+
+    let args = cli().get_matches_from([
+        "todosh",
+        "log",
+        "fixed bug",
+        "--tag",
+        "work",
+        "--priority",
+        "high",
+    ]);
+    let todo = todo_from_args(&config, &args, "fixed bug", "cli");
+    let id = log_todo(&config, todo, Some("was a typo"));
+
+    let logged = read_todos(&config).remove(1);
+    assert_eq!(id, "2");
+    assert!(logged.completed);
+    assert_eq!(logged.completed_at, logged.created_at);
+    assert!(logged.completed_at.is_some());
+    assert_eq!(logged.tags, "work");
+    assert_eq!(logged.priority, Priority::High);
+    assert_eq!(logged.notes, "was a typo");
+}
+
+#[test]
+fn finish_completes_and_archives_in_one_step() {
+    let (_dir, config) = temp_config();
+    add(&config, "Keep");
+    add(&config, "Finish");
+    add(&config, "Later");
+
+    finish_todo(&config, "2".to_owned(), Some("shipped"));
+
+    assert_eq!(
+        tasks(&config),
+        [
+            ("1".to_owned(), "Keep".to_owned()),
+            ("2".to_owned(), "Later".to_owned())
+        ]
+    );
+    let archive = Config {
+        database: config.archive_path(),
+        ..config.clone()
+    };
+    let archived = read_todos(&archive);
+    assert_eq!(archived.len(), 1);
+    assert_eq!(archived[0].task, "Finish");
+    assert!(archived[0].completed);
+    assert_eq!(archived[0].notes, "shipped");
+}
+
+#[test]
+fn each_list_has_its_own_archive() {
+    let (_dir, config) = temp_config();
+    let lists = ["home", "archive"].map(|name| Config {
+        database: config.list_path(name).unwrap(),
+        ..config.clone()
+    });
+
+    for (list, task) in lists.iter().zip(["Water plants", "Sort boxes"]) {
+        create_db_if_not_exists(list);
+        add(list, "Keep");
+        add(list, task);
+        finish_todo(list, "2".to_owned(), None);
+    }
+
+    for (list, task) in lists.iter().zip(["Water plants", "Sort boxes"]) {
+        assert_eq!(tasks(list), [("1".to_owned(), "Keep".to_owned())]);
+        let archive = Config {
+            database: list.archive_path(),
+            ..config.clone()
+        };
+        assert_eq!(tasks(&archive), [("2".to_owned(), task.to_owned())]);
+    }
+    assert!(!config.archive_path().exists());
+}
+
+#[test]
+fn auto_archive_moves_completed_todos_out_of_the_database() {
+    let (_dir, config) = temp_config();
+    let config = Config {
+        auto_archive_on_complete: true,
+        ..config
+    };
+    add(&config, "Keep");
+    add(&config, "Finish");
+
+    complete_todo(&config, "2".to_owned(), None);
+
+    assert_eq!(tasks(&config), [("1".to_owned(), "Keep".to_owned())]);
+    let archive = Config {
+        database: config.archive_path(),
+        ..config.clone()
+    };
+    let archived = read_todos(&archive);
+    assert_eq!(archived.len(), 1);
+    assert!(archived[0].completed && archived[0].task == "Finish");
+}
+
+#[test]
+fn pretty_json_is_indented_and_plain_json_is_compact() {
+    let (_dir, config) = temp_config();
+    add(&config, "Buy milk");
+    let json = Config {
+        format: OutputFormat::Json,
+        ..config
+    };
+
+    let compact = render_list(&json);
+    assert!(!compact.contains('\n'));
+
+    let pretty = render_list(&Config {
+        pretty: true,
+        ..json
+    });
+    assert!(pretty.contains("\n    \"TASK\": \"Buy milk\""));
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&pretty).unwrap(),
+        serde_json::from_str::<serde_json::Value>(&compact).unwrap()
+    );
+}
+
+#[test]
+fn expire_archives_only_stale_incomplete_todos() {
+    let (_dir, config) = temp_config();
+    let long_ago = Utc::now() - TimeDelta::try_days(120).unwrap();
+    let todo = |id, task: &str, completed| Todo {
+        created_at: Some(long_ago),
+        completed,
+        ..Todo::new(id, task)
+    };
+    let records = vec![
+        todo(1, "Learn the harp", false),
+        todo(2, "Old but done", true),
+        Todo::new(3, "Fresh"),
+    ];
+    write_to_database(&config, records);
+
+    expire_todos(
+        &config,
+        Utc::now() - TimeDelta::try_days(90).unwrap(),
+        false,
+        true,
+    );
+
+    assert_eq!(
+        tasks(&config),
+        [
+            ("1".to_owned(), "Old but done".to_owned()),
+            ("2".to_owned(), "Fresh".to_owned())
+        ]
+    );
+    let archive = Config {
+        database: config.archive_path(),
+        ..config.clone()
+    };
+    assert_eq!(
+        tasks(&archive),
+        [("1".to_owned(), "Learn the harp".to_owned())]
+    );
+}
+
+#[test]
+fn create_returns_the_assigned_id() {
+    let (_dir, config) = temp_config();
+    add(&config, "First");
+
+    assert_eq!(create_todo(&config, Todo::new(0, "Second"), false), "2");
+    assert_eq!(create_todo(&config, Todo::new(0, "Top"), true), "1");
+    assert_eq!(tasks(&config)[0].1, "Top");
+}
+
+#[test]
+fn merge_dedupes_by_text_and_prefers_completed() {
+    let todo = |id, task: &str, completed| Todo {
+        completed,
+        ..Todo::new(id, task)
+    };
+    let mut records = vec![todo(1, "Buy milk", false), todo(2, "Call bank", true)];
+    let other = vec![
+        todo(1, "buy milk", true),
+        todo(2, "Call bank", true),
+        todo(3, "Paint fence", false),
+        Todo {
+            parent_id: "3".to_owned(),
+            ..todo(4, "Buy brushes", false)
+        },
+    ];
+
+    let counts = merge::merge(&mut records, other);
+
+    assert_eq!(
+        counts,
+        merge::Counts {
+            merged: 2,
+            duplicates: 1,
+            conflicts: 1
+        }
+    );
+    assert!(records[0].completed);
+    let merged: Vec<(&str, &str, &str)> = records[2..]
+        .iter()
+        .map(|t| (t.id.as_str(), t.task.as_str(), t.parent_id.as_str()))
+        .collect();
+    assert_eq!(
+        merged,
+        [("3", "Paint fence", ""), ("4", "Buy brushes", "3")]
+    );
+}
+
+#[test]
+fn sort_spec_breaks_ties_with_later_keys() {
+    let todo = |id, due: Option<&str>, completed| Todo {
+        due: due.and_then(parse_due),
+        completed,
+        ..Todo::new(id, "task")
+    };
+    let order = |spec, nulls_first| {
+        let mut todos = vec![
+            todo(1, Some("2026-11-01"), false),
+            todo(2, None, false),
+            todo(3, Some("2026-10-01"), true),
+            todo(4, Some("2026-10-20"), false),
+        ];
+        let config = Config {
+            sort: SortBy::parse_spec(spec).unwrap(),
+            nulls_first,
+            ..Config::default()
+        };
+        sort_listed(&config, &mut todos);
+        todos.into_iter().map(|t| t.id).collect::<Vec<String>>()
+    };
+
+    assert_eq!(order("due", false), ["3", "4", "1", "2"]);
+    assert_eq!(order("completed,due", false), ["4", "1", "2", "3"]);
+    assert_eq!(order("completed,due", true), ["2", "4", "1", "3"]);
+    assert_eq!(order("completed,id", false), ["1", "2", "4", "3"]);
+    assert!(SortBy::parse_spec("completed,colour").is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn format_cmd_receives_the_listed_todos_as_json() {
+    let (dir, config) = temp_config();
+    add(&config, "Buy milk");
+    add(&config, "Walk dog");
+    let out = dir.path().join("out.json");
+
+    run_formatter(&config, &format!("cat > '{}'", out.display()));
+
+    let todos: Vec<serde_json::Value> =
+        serde_json::from_str(&fs::read_to_string(&out).unwrap()).unwrap();
+    assert_eq!(todos.len(), 2);
+    assert_eq!(todos[1]["TASK"], "Walk dog");
+}
+
+#[test]
+fn list_filters_are_remembered_per_list() {
+    let (_dir, config) = temp_config();
+    let home = Config {
+        database: config.list_path("home").unwrap(),
+        ..config.clone()
+    };
+    let list = |flags: &[&str]| cli().get_matches_from(["todosh", "list"].iter().chain(flags));
+
+    assert!(remember::apply(&config, &list(&["--only-incomplete", "--sort", "due"])).is_empty());
+    assert_eq!(
+        remember::apply(&config, &list(&[])),
+        ["--only-incomplete", "--sort=due"]
+    );
+    assert!(remember::apply(&home, &list(&[])).is_empty());
+
+    assert!(remember::apply(&config, &list(&["--default"])).is_empty());
+    assert!(remember::apply(&config, &list(&[])).is_empty());
+}
+
+#[test]
+fn list_new_shows_changes_since_the_last_run() {
+    let (_dir, mut config) = temp_config();
+    let now = Utc::now();
+    let old = |task| Todo {
+        created_at: Some(now - TimeDelta::hours(1)),
+        ..Todo::new(0, task)
+    };
+    create_todos(&config, vec![old("untouched"), old("finished")], false);
+
+    remember::apply(
+        &config,
+        &cli().get_matches_from(["todosh", "list", "--all"]),
+    );
+    remember::record_run(&config, now - TimeDelta::minutes(1));
+    assert_eq!(
+        remember::last_run(&config),
+        Some(now - TimeDelta::minutes(1))
+    );
+
+    add(&config, "fresh");
+    complete_todo(&config, "2".to_owned(), None);
+
+    config.since = remember::last_run(&config);
+    let tasks: Vec<String> = listed_todos(&config).into_iter().map(|t| t.task).collect();
+    assert_eq!(tasks, ["finished", "fresh"]);
+
+    let list = cli().get_matches_from(["todosh", "list"]);
+    assert_eq!(remember::apply(&config, &list), ["--all"]);
+}
+
+#[test]
+fn search_looks_in_the_chosen_field_and_highlights_the_task() {
+    let todo = Todo {
+        tags: "Work".to_owned(),
+        notes: "call Dana about the report".to_owned(),
+        ..Todo::new(1, "Write report")
+    };
+
+    assert!(matches_query(&todo, "report", SearchField::Task));
+    assert!(!matches_query(&todo, "dana", SearchField::Task));
+    assert!(matches_query(&todo, "dana", SearchField::Notes));
+    assert!(matches_query(&todo, "work", SearchField::Tags));
+    assert!(!matches_query(&todo, "work", SearchField::Notes));
+    assert!(matches_query(&todo, "dana", SearchField::All));
+
+    assert_eq!(
+        highlight(" Write REPORT ", "report"),
+        " Write \u{1b}[7mREPORT\u{1b}[27m "
+    );
+}
+
+#[test]
+fn encrypted_files_open_only_with_the_right_passphrase() {
+    let (dir, config) = temp_config();
+    let salt = [7; 16];
+    let key = storage::derive_key("correct horse", &salt);
+
+    let sealed = storage::seal(&key, &salt, b"ID,TASK\n1,secret\n");
+    assert!(!sealed.windows(6).any(|window| window == b"secret"));
+
+    let path = dir.path().join("sealed.csv");
+    fs::write(&path, &sealed).unwrap();
+    assert!(storage::is_encrypted(&path));
+    assert!(!storage::is_encrypted(&config.database));
+
+    let body = sealed
+        .strip_prefix(b"todosh-encrypted-v1\n".as_slice())
+        .unwrap();
+    let (found, body) = storage::split_sealed(body).unwrap();
+    assert_eq!(found, salt);
+    assert_eq!(
+        storage::unseal(&key, body).as_deref(),
+        Some(b"ID,TASK\n1,secret\n".as_slice())
+    );
+    assert_eq!(
+        storage::unseal(&storage::derive_key("wrong", &salt), body),
+        None
+    );
+
+    let mut tampered = body.to_vec();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert_eq!(storage::unseal(&key, &tampered), None);
+
+    // Plain files pass through untouched.
+    assert_eq!(
+        storage::decrypt(&config, b"ID,TASK\n".to_vec()).unwrap(),
+        b"ID,TASK\n"
+    );
+}
+
+#[test]
+fn json_backend_round_trips_and_migrates() {
+    let (dir, config) = temp_config();
+    add(&config, "plain");
+    let notes = Todo {
+        notes: "first line\nsecond, \"quoted\"".to_owned(),
+        ..Todo::new(0, "with notes")
+    };
+    create_todos(&config, vec![notes], false);
+    complete_todo(&config, "1".to_owned(), None);
+
+    migrate_database(&config, Backend::Json);
+    let json = Config {
+        database: dir.path().join("db.json"),
+        backend: Backend::Json,
+        ..config.clone()
+    };
+
+    let stored: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&json.database).unwrap()).unwrap();
+    assert_eq!(stored[1]["NOTES"], "first line\nsecond, \"quoted\"");
+    assert_eq!(stored[0]["COMPLETED"], true);
+
+    add(&json, "third");
+    let todos = read_todos(&json);
+    assert_eq!(todos.len(), 3);
+    assert!(todos[0].completed);
+    assert_eq!(todos[1].notes, "first line\nsecond, \"quoted\"");
+    assert_eq!(todos[2].id, "3");
+    assert_eq!(read_todos(&config).len(), 2);
+}
+
+#[test]
+fn list_filters_by_relative_age() {
+    let (_dir, mut config) = temp_config();
+    let aged = |task, days| Todo {
+        created_at: Some(Utc::now() - TimeDelta::days(days)),
+        ..Todo::new(0, task)
+    };
+    create_todos(
+        &config,
+        vec![aged("ancient", 30), aged("week", 5), aged("today", 0)],
+        false,
+    );
+    complete_todo(&config, "1".to_owned(), None);
+    let listed = |config: &Config| -> Vec<String> {
+        listed_todos(config).into_iter().map(|t| t.task).collect()
+    };
+
+    config.created_before = age_cutoff(&config, Some("7d"));
+    assert_eq!(listed(&config), ["ancient"]);
+
+    config.only_incomplete = true;
+    assert!(listed(&config).is_empty());
+
+    config.only_incomplete = false;
+    config.created_before = age_cutoff(&config, Some("1d"));
+    config.created_after = age_cutoff(&config, Some("2w"));
+    assert_eq!(listed(&config), ["week"]);
+}
+
+#[test]
+fn complete_undo_reopens_only_the_last_completion() {
+    let (_dir, config) = temp_config();
+    add(&config, "parent");
+    add(&config, "other");
+    create_todos(
+        &config,
+        vec![Todo {
+            parent_id: "1".to_owned(),
+            ..Todo::new(0, "child")
+        }],
+        false,
+    );
+
+    complete_todo(&config, "2".to_owned(), None);
+    complete_todo(&config, "1".to_owned(), None);
+    assert!(read_todos(&config).iter().all(|todo| todo.completed));
+
+    undo_completion(&config);
+    let completed: Vec<bool> = read_todos(&config).iter().map(|t| t.completed).collect();
+    assert_eq!(completed, [false, true, false]);
+    assert!(remember::take_completion(&config).is_empty());
+}
+
+#[test]
+fn column_widths_measure_display_width() {
+    let todos = [Todo::new(1, "漢字 task"), Todo::new(10, "x")];
+    let table = Table::new(&todos);
+
+    assert_eq!(column_widths(&table), [2, 9, 9]);
+}
+
+#[test]
+fn emoji_and_cjk_tasks_stay_aligned() {
+    let (_dir, config) = temp_config();
+    let config = Config {
+        show_footer: false,
+        ..config
+    };
+    add(&config, "🎉 party");
+    add(&config, "漢字 task");
+
+    let line_widths = |table: &str| -> BTreeSet<usize> {
+        assert!(table.contains("🎉 party") && table.contains("漢字 task"));
+        table.lines().map(display_width).collect()
+    };
+
+    assert_eq!(line_widths(&render_list(&config)).len(), 1);
+
+    let mut streamed = Vec::new();
+    stream_todos(&config, &mut streamed);
+    let streamed = String::from_utf8(streamed).unwrap();
+    assert_eq!(line_widths(&streamed).len(), 1);
+    assert_eq!(line_widths(&streamed), line_widths(&render_list(&config)));
+}
+
+#[test]
+fn source_is_recorded_and_old_rows_read_as_unknown() {
+    let (_dir, config) = temp_config();
+    fs::write(
+        &config.database,
+        "ID,TASK,COMPLETED,ESTIMATE\n1,from before,false,\n",
+    )
+    .unwrap();
+
+    let args = cli().get_matches_from(["todosh", "create"]);
+    create_todo(
+        &config,
+        todo_from_args(&config, &args, "typed", "cli"),
+        false,
+    );
+    create_todo(
+        &config,
+        todo_from_args(&config, &args, "piped", "stdin"),
+        false,
+    );
+
+    let sources: Vec<String> = read_todos(&config)
+        .iter()
+        .map(|todo| todo.source_name().to_owned())
+        .collect();
+    assert_eq!(sources, ["unknown", "cli", "stdin"]);
+}
+
+#[test]
+fn reorder_follows_the_file_and_keeps_subtasks() {
+    let (dir, config) = temp_config();
+    add(&config, "first");
+    add(&config, "second");
+    add_subtask(&config, "1", Todo::new(0, "child"));
+
+    let order = dir.path().join("order.txt");
+    fs::write(
+        &order,
+        "3\tfalse\tchild\n2\tfalse\tsecond\n1\tfalse\tfirst\n",
+    )
+    .unwrap();
+    reorder_todos(&config, order.to_str().unwrap());
+
+    let todos = read_todos(&config);
+    let tasks: Vec<&str> = todos.iter().map(|todo| todo.task.as_str()).collect();
+    assert_eq!(tasks, ["child", "second", "first"]);
+    assert_eq!(todos[0].parent_id, "3");
+}
+
+#[test]
+fn pager_follows_threshold_unless_overridden() {
+    let mut config = Config {
+        max_rows: 10,
+        ..Config::default()
+    };
+    assert!(!wants_pager(&config, 10));
+    assert!(wants_pager(&config, 11));
+
+    config.max_rows = 0;
+    assert!(!wants_pager(&config, 1000));
+
+    apply_args(
+        &mut config,
+        &cli().get_matches_from(["todosh", "list", "--pager"]),
+    );
+    assert!(wants_pager(&config, 1));
+
+    apply_args(
+        &mut config,
+        &cli().get_matches_from(["todosh", "list", "--no-pager"]),
+    );
+    config.max_rows = 10;
+    assert!(!wants_pager(&config, 11));
+}
+
+#[test]
+fn not_found_suggests_the_nearest_id() {
+    let todos: Vec<Todo> = (1..=9).map(|id| Todo::new(id, "Task")).collect();
+
+    assert_eq!(
+        not_found_in(&todos, "12").to_string(),
+        "error: No Todo with ID 12; IDs run from 1 to 9, did you mean 9?"
+    );
+    assert_eq!(
+        serde_json::to_string(&not_found_in(&todos, "0")).unwrap(),
+        r#"{"error":"not_found","id":"0","first":"1","last":"9","closest":"1"}"#
+    );
+    assert_eq!(
+        not_found_in(&[], "3").to_string(),
+        "error: No Todo with ID 3"
+    );
+}
+
+#[test]
+fn messages_follow_the_language_and_fall_back_to_english() {
+    let placeholders = |template: &str| {
+        let mut names: Vec<String> = template
+            .split('{')
+            .skip(1)
+            .filter_map(|part| part.split_once('}').map(|(name, _)| name.to_owned()))
+            .collect();
+        names.sort();
+        names
+    };
+
+    for (key, template) in i18n::DE {
+        let english = i18n::EN.iter().find(|(name, _)| name == key);
+        assert_eq!(
+            english.map(|(_, english)| placeholders(english)),
+            Some(placeholders(template)),
+            "{key}"
+        );
+    }
+
+    assert_eq!(i18n::Lang::parse("de_DE.UTF-8"), Some(i18n::Lang::De));
+    assert_eq!(i18n::Lang::parse("C"), Some(i18n::Lang::En));
+    assert_eq!(i18n::Lang::parse("fr_FR"), None);
+
+    let mut config = Config::default();
+    assert_eq!(
+        i18n::text(&config, "deleted", &[("task", &"{id}"), ("id", &3)]),
+        "Deleted task \"{id}\" with ID \"3\""
+    );
+
+    config.lang = Some(i18n::Lang::De);
+    assert_eq!(
+        i18n::text(&config, "some-left", &[("count", &4)]),
+        "Super! Noch 4 Aufgaben."
+    );
+}
+
+#[test]
+fn errors_are_translated_too() {
+    for (key, _) in i18n::EN {
+        assert!(i18n::DE.iter().any(|(name, _)| name == key), "{key}");
+    }
+
+    let error = || AppError::NotFound { id: "3".to_owned() }.to_string();
+
+    i18n::activate(Some(i18n::Lang::De));
+    assert_eq!(error(), "error: Kein Todo mit ID 3");
+    assert_eq!(
+        AppError::MissingScript.to_string(),
+        "error: Aufruf: todosh run <file>"
+    );
+
+    i18n::activate(None);
+    assert_eq!(error(), "error: No Todo with ID 3");
+}
+
+#[test]
+fn command_output_is_translated() {
+    let config = Config {
+        lang: Some(i18n::Lang::De),
+        ..Config::default()
+    };
+
+    assert_eq!(progress_footer(&config, 5, 12), "5/12 erledigt (42%)");
+    assert_eq!(
+        stats::goal_progress(&config, 5, 5),
+        "5/5 heute [#####] Ziel erreicht"
+    );
+
+    i18n::activate(config.lang);
+    let invalid = AppError::InvalidValue {
+        value: "viele".to_owned(),
+        expected: i18n::text(&config, "expected-goal", &[]),
+    };
+    assert_eq!(
+        invalid.to_string(),
+        "error: Ungültiger Wert 'viele', erwartet wird eine Anzahl Todos pro Tag, oder 0 für kein Ziel"
+    );
+    i18n::activate(None);
+}
+
+#[test]
+fn raise_and_lower_saturate() {
+    let (_dir, config) = temp_config();
+    add(&config, "Task");
+    let priority = || read_todos(&config)[0].priority;
+
+    shift_priority(&config, "1", true);
+    assert_eq!(priority(), Priority::High);
+    shift_priority(&config, "1", true);
+    assert_eq!(priority(), Priority::High);
+
+    shift_priority(&config, "1", false);
+    shift_priority(&config, "1", false);
+    shift_priority(&config, "1", false);
+    assert_eq!(priority(), Priority::Low);
+}
+
+#[test]
+fn list_info_names_the_list() {
+    let (_dir, config) = temp_config();
+    assert!(meta::header(&config).is_none());
+
+    meta::run(&config, &["set", "name", "Day", "job"], None);
+    assert_eq!(meta::header(&config).unwrap(), "Day job");
+
+    let home = Config {
+        database: config.list_path("home").unwrap(),
+        ..config.clone()
+    };
+    meta::run(&home, &[], Some("Chores"));
+    assert_eq!(meta::header(&home).unwrap(), "home\nChores");
+
+    let work = Config {
+        database: config.list_path("work").unwrap(),
+        ..config.clone()
+    };
+    fs::create_dir(work.meta_path()).unwrap();
+    assert!(matches!(
+        meta::ListMeta::default().save(&work),
+        Err(AppError::WriteFailed { .. })
+    ));
+}
+
+#[test]
+fn goal_progress_bar_scales_and_saturates() {
+    assert_eq!(
+        stats::goal_progress(&Config::default(), 0, 5),
+        "0/5 today [-----]"
+    );
+    assert_eq!(
+        stats::goal_progress(&Config::default(), 3, 5),
+        "3/5 today [###--]"
+    );
+    assert_eq!(
+        stats::goal_progress(&Config::default(), 7, 5),
+        "7/5 today [#####] goal reached"
+    );
+    assert_eq!(
+        stats::goal_progress(&Config::default(), 15, 30),
+        "15/30 today [#####-----]"
+    );
+}
+
+#[test]
+fn color_and_task_color_are_separate_flags() {
+    let config = Config::default();
+    let args = cli().get_matches_from([
+        "todosh",
+        "create",
+        "x",
+        "--task-color",
+        "red",
+        "--color",
+        "never",
+    ]);
+
+    assert!(color::color_enabled(Some("always")));
+    assert!(!color::color_enabled(Some("never")));
+    assert_eq!(todo_from_args(&config, &args, "x", "cli").color, "red");
+
+    let mut config = Config::default();
+    apply_args(&mut config, &args);
+    assert!(!config.color);
+
+    let palette_as_mode = cli().get_matches_from_safe(["todosh", "list", "--color", "red"]);
+    assert!(palette_as_mode.is_err());
+}
+
+#[test]
+fn templates_expand_date_placeholder() {
+    let config = Config {
+        templates: [("review".to_owned(), "Review {date} notes".to_owned())].into(),
+        ..Config::default()
+    };
+    let today = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+
+    assert_eq!(
+        expand_template(&config, "review", today),
+        "Review 2026-03-02 notes"
+    );
+}
+
+#[test]
+fn html_export_escapes_task_text() {
+    let (dir, config) = temp_config();
+    add(&config, "<script>alert('x')</script> & co");
+    let config = Config {
+        format: OutputFormat::Html,
+        ..config
+    };
+    let out = dir.path().join("todos.html");
+
+    export_todos(&config, out.to_str(), false);
+
+    let page = fs::read_to_string(out).unwrap();
+    assert!(page.starts_with("<!DOCTYPE html>"));
+    assert!(page.contains("&lt;script&gt;alert(&#39;x&#39;)&lt;/script&gt; &amp; co"));
+    assert!(!page.contains("<script>"));
+}
+
+#[test]
+fn create_rewrites_a_stale_header_before_appending() {
+    let (_dir, config) = temp_config();
+
+    for header in ["ID,TASK,COMPLETED", "TASK,ID,COMPLETED"] {
+        let old: Vec<&str> = header.split(',').collect();
+        let row = if old[0] == "ID" {
+            "1,Old,true"
+        } else {
+            "Old,1,true"
+        };
+        fs::write(&config.database, format!("{header}\n{row}\n")).unwrap();
+
+        add(&config, "New");
+
+        let contents = fs::read_to_string(&config.database).unwrap();
+        assert_eq!(contents.lines().next(), Some(COLUMNS.join(",").as_str()));
+        assert_eq!(
+            tasks(&config),
+            [
+                ("1".to_owned(), "Old".to_owned()),
+                ("2".to_owned(), "New".to_owned())
+            ]
+        );
+        assert!(read_todos(&config)[0].completed);
+    }
+}
+
+#[test]
+fn no_auto_create_overrides_config() {
+    let mut config = Config::default();
+    assert!(config.auto_create);
+
+    apply_args(
+        &mut config,
+        &cli().get_matches_from(["todosh", "list", "--no-auto-create"]),
+    );
+    assert!(!config.auto_create);
+}
+
+#[test]
+fn read_only_reads_without_touching_the_database() {
+    let (dir, mut config) = temp_config();
+    fs::write(
+        &config.database,
+        format!("{}\n1,Task 1,false,,,,,,,,,,,\n", COLUMNS.join(",")),
+    )
+    .unwrap();
+    let before = fs::read(&config.database).unwrap();
+
+    apply_args(
+        &mut config,
+        &cli().get_matches_from(["todosh", "list", "--read-only"]),
+    );
+    assert!(config.read_only);
+
+    assert_eq!(read_todos(&config).len(), 1);
+    assert_eq!(fs::read(&config.database).unwrap(), before);
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn picker_retries_until_a_valid_choice() {
+    let config = Config::default();
+    let todos = [Todo::new(1, "write report"), Todo::new(2, "read report")];
+    let candidates: Vec<&Todo> = todos.iter().collect();
+
+    assert_eq!(
+        pick_todo(&config, &mut "0\nx\n2\n".as_bytes(), &candidates).as_deref(),
+        Some("2")
+    );
+    assert_eq!(pick_todo(&config, &mut "\n".as_bytes(), &candidates), None);
+}
+
+#[test]
+fn flat_export_is_one_line() {
+    let mut done = Todo::new(3, "file\ntaxes");
+    done.completed = true;
+    let todos = [
+        Todo::new(1, "buy milk"),
+        Todo::new(2, "call  dentist"),
+        done,
+    ];
+
+    assert_eq!(
+        flat_line(&todos),
+        "buy milk; call dentist; [done] file taxes"
+    );
+}
+
+#[test]
+fn list_orders_scrambled_rows_by_id() {
+    let (_dir, config) = temp_config();
+    let rows: String = [3, 10, 1, 2]
+        .map(|id| format!("{id},Task {id},false,,,,,,,,,,,\n"))
+        .concat();
+    fs::write(&config.database, format!("{}\n{rows}", COLUMNS.join(","))).unwrap();
+
+    let config = Config {
+        format: OutputFormat::Json,
+        ..config
+    };
+    let listed: Vec<serde_json::Value> = serde_json::from_str(&render_list(&config)).unwrap();
+    let ids: Vec<&str> = listed
+        .iter()
+        .map(|todo| todo["ID"].as_str().unwrap())
+        .collect();
+
+    assert_eq!(ids, ["1", "2", "3", "10"]);
+}
+
+#[test]
+fn normalize_canonicalizes_booleans_and_padding() {
+    let (_dir, config) = temp_config();
+    let rows = "1, padded\t,TRUE,,,,,,medium,,,,,0\n2,yes,Yes,,,,,,medium,,,,,0\n3,kept,false,,,,,,medium,,,,,0\n";
+    fs::write(&config.database, format!("{}\n{rows}", COLUMNS.join(","))).unwrap();
+
+    assert_eq!(normalize_database(&config), 3);
+
+    let contents = fs::read_to_string(&config.database).unwrap();
+    assert!(contents.contains("\n1,padded,true,"));
+    assert!(contents.contains("\n2,yes,true,"));
+    assert_eq!(normalize_database(&config), 0);
+}
+
+#[test]
+fn workload_sums_open_estimates() {
+    let (_dir, config) = temp_config();
+    let args = |estimate| cli().get_matches_from(["todosh", "create", "--estimate", estimate]);
+    create_todo(
+        &config,
+        todo_from_args(&config, &args("30"), "Draft", "cli"),
+        false,
+    );
+    create_todo(
+        &config,
+        todo_from_args(&config, &args("15"), "Review", "cli"),
+        false,
+    );
+    add(&config, "Unestimated");
+    complete_todo(&config, "2".to_owned(), None);
+
+    let todos = read_todos(&config);
+    assert_eq!(todos[0].estimate, Some(30));
+
+    let open: Vec<&Todo> = todos.iter().filter(|todo| !todo.completed).collect();
+    assert_eq!(workload(&open), (30, 1));
+}
+
+#[test]
+fn tag_progress_counts_each_tag_and_sorts_by_ratio() {
+    let todo = |id, tags: &str, completed| Todo {
+        tags: tags.to_owned(),
+        completed,
+        ..Todo::new(id, "Task")
+    };
+    let todos = [
+        todo(1, "work, home", true),
+        todo(2, "work", false),
+        todo(3, "home", true),
+        todo(4, "errands,errands", false),
+        todo(5, "", false),
+    ];
+
+    assert_eq!(
+        tag_progress(&todos),
+        [("errands", 0, 1), ("work", 1, 2), ("home", 2, 2)]
+    );
+}
+
+#[test]
+fn concurrent_appends_keep_rows_whole() {
+    let (_dir, config) = temp_config();
+    add(&config, "seed");
+
+    std::thread::scope(|scope| {
+        for writer in 0..8 {
+            let config = &config;
+            scope.spawn(move || {
+                for n in 0..25 {
+                    let task = format!("writer {writer} todo {n} {}", "x".repeat(200));
+                    create_todos(config, vec![Todo::new(0, &task)], false);
+                }
+            });
+        }
+    });
+
+    let contents = fs::read_to_string(&config.database).unwrap();
+    let mut reader = ReaderBuilder::new().from_reader(contents.as_bytes());
+    let tasks: HashSet<String> = reader
+        .records()
+        .map(|row| {
+            let row = row.unwrap();
+            assert_eq!(row.len(), COLUMNS.len());
+            row[1].to_owned()
+        })
+        .collect();
+
+    assert_eq!(tasks.len(), 1 + 8 * 25);
+    assert!(tasks.contains(&format!("writer 7 todo 24 {}", "x".repeat(200))));
+}
+
+#[test]
+fn completed_order_groups_stably() {
+    let mut todos: Vec<Todo> = (1..=4).map(|id| Todo::new(id, "Task")).collect();
+    todos[1].completed = true;
+    todos[3].completed = true;
+    let order = |completed_order| {
+        let config = Config {
+            completed_order,
+            ..Config::default()
+        };
+        let mut todos = todos.clone();
+        sort_listed(&config, &mut todos);
+        todos
+            .into_iter()
+            .map(|todo| todo.id)
+            .collect::<Vec<String>>()
+    };
+
+    assert_eq!(order(CompletedOrder::Mixed), ["1", "2", "3", "4"]);
+    assert_eq!(order(CompletedOrder::First), ["2", "4", "1", "3"]);
+    assert_eq!(order(CompletedOrder::Last), ["1", "3", "2", "4"]);
+}
+
+#[test]
+fn bulk_delete_resolves_ids_before_renumbering() {
+    let (_dir, config) = temp_config();
+    for task in ["a", "b", "c", "d"] {
+        add(&config, task);
+    }
+    let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<String>>();
+
+    assert_eq!(
+        bulk::delete(&config, &ids(&["1", "3", "3", "9"])),
+        ExitCode::FAILURE
+    );
+    let left: Vec<String> = tasks(&config).into_iter().map(|(_, task)| task).collect();
+    assert_eq!(left, ["b", "d"]);
+
+    assert_eq!(
+        bulk::complete(&config, &ids(&["1", "2"]), None),
+        ExitCode::SUCCESS
+    );
+    assert!(read_todos(&config).iter().all(|todo| todo.completed));
+}
+
+#[test]
+fn completions_are_counted_and_taken_back_on_reopen() {
+    let (_dir, config) = temp_config();
+    add(&config, "water plants");
+
+    complete_todo(&config, "1".to_owned(), None);
+    assert_eq!(read_todos(&config)[0].times_completed, 1);
+
+    reopen_todo(&config, Some("1".to_owned()));
+    assert_eq!(read_todos(&config)[0].times_completed, 0);
+
+    complete_todo(&config, "1".to_owned(), None);
+    assert_eq!(read_todos(&config)[0].times_completed, 1);
+
+    // Databases from before the column was added read as never completed.
+    let old_header = &COLUMNS[..COLUMNS.len() - 1];
+    fs::write(
+        &config.database,
+        format!("{}\n1,old,true,,,,,,,,,,\n", old_header.join(",")),
+    )
+    .unwrap();
+    assert_eq!(read_todos(&config)[0].times_completed, 0);
+}
+
+#[test]
+fn rewrites_refuse_a_database_with_a_malformed_row() {
+    let (_dir, config) = temp_config();
+    let rows = "1,ok,false,,,,,,,,,,,\n2,bad,maybe,,,,,,,,,,,\n3,also ok,false,,,,,,,,,,,\n";
+    fs::write(&config.database, format!("{}\n{rows}", COLUMNS.join(","))).unwrap();
+    let before = fs::read(&config.database).unwrap();
+
+    assert!(matches!(
+        records_to_rewrite(&config),
+        Err(AppError::MalformedRow { line: Some(3), .. })
+    ));
+    assert_eq!(fs::read(&config.database).unwrap(), before);
+
+    fs::write(
+        &config.database,
+        format!("{}\n1,ok,false,,,,,,,,,,,\n", COLUMNS.join(",")),
+    )
+    .unwrap();
+    complete_todo(&config, "1".to_owned(), None);
+    assert!(read_todos(&config)[0].completed);
+}
@@ -0,0 +1,46 @@
+use chrono::{DateTime, FixedOffset, Local, Offset, Utc};
+use serde::{Deserialize, Deserializer, de::Error};
+
+/// Zone timestamps are shown in. They are always stored in UTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Timezone {
+    /// The machine's local zone, including its daylight saving changes.
+    #[default]
+    Local,
+    Fixed(FixedOffset),
+}
+
+impl Timezone {
+    /// Parses `local`, `utc` or an offset such as `+02:00` or `-0530`.
+    pub fn parse(value: &str) -> Option<Timezone> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "local" => Some(Timezone::Local),
+            "utc" | "z" => Some(Timezone::Fixed(Utc.fix())),
+            offset => offset.parse().ok().map(Timezone::Fixed),
+        }
+    }
+
+    /// Formats `at` for display, e.g. `2026-03-02 04:15:00 +05:30`.
+    pub fn render(self, at: DateTime<Utc>) -> String {
+        let offset = match self {
+            Timezone::Local => at.with_timezone(&Local).offset().fix(),
+            Timezone::Fixed(offset) => offset,
+        };
+
+        at.with_timezone(&offset)
+            .format("%Y-%m-%d %H:%M:%S %:z")
+            .to_string()
+    }
+}
+
+impl<'de> Deserialize<'de> for Timezone {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Timezone, D::Error> {
+        let value = String::deserialize(deserializer)?;
+
+        Timezone::parse(&value).ok_or_else(|| {
+            D::Error::custom(format!(
+                "invalid timezone {value:?}, expected \"local\", \"utc\" or an offset like \"+02:00\""
+            ))
+        })
+    }
+}